@@ -0,0 +1,292 @@
+//! Route attribute macros for [Amiya], use them by enable the `macros` feature of `amiya`.
+//!
+//! An attribute like `#[get("/users/{id}")]` converts a async fn to a handler type which has the
+//! same name, it implements `Middleware` and `Handler` trait, so you can add it to a `Router` by
+//! `Router::handler`.
+//!
+//! The first argument of the fn is the `Context`, others are path arguments, their names must be
+//! args in the path, and their types must implement `FromStr`.
+//!
+//! There is a attribute for every method `MethodRouter` supports, named as it's method shortcut,
+//! like `#[prop_find("/dav/{*path}")]` and `#[r#move("/dav/{*path}")]`.
+//!
+//! Generated code refers to `amiya` by the name in your `Cargo.toml`, so it works when the
+//! dependency is renamed.
+//!
+//! See document of `amiya::middleware::Handler` for details.
+//!
+//! [Amiya]: https://docs.rs/amiya
+
+#![deny(warnings)]
+#![deny(clippy::all, clippy::pedantic, clippy::nursery)]
+#![deny(missing_debug_implementations, rust_2018_idioms)]
+#![forbid(unsafe_code, missing_docs)]
+
+use {
+    proc_macro::TokenStream,
+    proc_macro2::TokenStream as TokenStream2,
+    proc_macro_crate::{crate_name, FoundCrate},
+    quote::{format_ident, quote, quote_spanned},
+    syn::{
+        spanned::Spanned, Error, FnArg, GenericArgument, Ident, ItemFn, LitStr, Pat, PathArguments,
+        Signature, Type,
+    },
+};
+
+/// Arg names in `path`, like `id` and `path` in `/users/{id:int}/{*path}`.
+fn path_args(path: &str) -> Result<Vec<&str>, &'static str> {
+    let mut args = vec![];
+    let mut rest = path;
+    while let Some(start) = rest.find(['{', '}']) {
+        if rest[start..].starts_with('}') {
+            return Err("unexpected `}` in path");
+        }
+        let param = &rest[start + 1..];
+        let mut depth = 0_usize;
+        let end = param
+            .char_indices()
+            .find(|&(_, c)| match c {
+                '{' => {
+                    depth += 1;
+                    false
+                }
+                '}' if depth == 0 => true,
+                '}' => {
+                    depth -= 1;
+                    false
+                }
+                _ => false,
+            })
+            .map(|(i, _)| i)
+            .ok_or("missing `}` in path")?;
+        let name = param[..end].split(':').next().unwrap_or_default().trim_start_matches('*');
+        if name.is_empty() {
+            return Err("path arg name is empty");
+        }
+        args.push(name);
+        rest = &param[end + 1..];
+    }
+    Ok(args)
+}
+
+/// Path of the `amiya` crate in the crate using the macros.
+fn amiya_path() -> TokenStream2 {
+    match crate_name("amiya") {
+        Ok(FoundCrate::Name(name)) => {
+            let name = format_ident!("{}", name);
+            quote!(::#name)
+        }
+        // `Itself` is only found in examples and doc tests of amiya, the lib never uses the
+        // macros, so the crate is `amiya` as usual
+        Ok(FoundCrate::Itself) | Err(_) => quote!(::amiya),
+    }
+}
+
+/// The `Ex` type of `Context<'_, Ex>`.
+fn extra_type(ty: &Type) -> Option<&Type> {
+    let Type::Path(path) = ty else { return None };
+    let segment = path.path.segments.last().filter(|s| s.ident == "Context")?;
+    let PathArguments::AngleBracketed(ref args) = segment.arguments else { return None };
+    args.args.iter().find_map(|arg| match arg {
+        GenericArgument::Type(ty) => Some(ty),
+        _ => None,
+    })
+}
+
+/// The `Ex` type, and statements to parse path args to variables, of handler fn `sig`.
+fn handler_args<'a>(
+    sig: &'a Signature, names: &[&str], path: &str,
+) -> Result<(&'a Type, Vec<TokenStream2>, Vec<Ident>), Error> {
+    let mut inputs = sig.inputs.iter();
+    let ex = match inputs.next() {
+        Some(FnArg::Typed(ctx)) => extra_type(&ctx.ty).ok_or_else(|| {
+            Error::new_spanned(&ctx.ty, "first argument must be `Context<'_, Ex>`")
+        })?,
+        Some(arg) => {
+            return Err(Error::new_spanned(arg, "first argument must be `Context<'_, Ex>`"))
+        }
+        None => return Err(Error::new(sig.paren_token.span.join(), "missing `Context` argument")),
+    };
+
+    let mut parses = vec![];
+    let mut vars = vec![];
+    for (i, arg) in inputs.enumerate() {
+        let FnArg::Typed(arg) = arg else { unreachable!("self is only allowed in the first") };
+        let name = match *arg.pat {
+            Pat::Ident(ref pat) => pat.ident.to_string(),
+            _ => return Err(Error::new_spanned(&arg.pat, "path argument must be a identifier")),
+        };
+        let name = name.trim_start_matches("r#");
+        if !names.contains(&name) {
+            let message = format!("`{name}` is not an arg of route path `{path}`");
+            return Err(Error::new(arg.pat.span(), message));
+        }
+        let (var, ty) = (format_ident!("__arg_{}", i), &arg.ty);
+        parses.push(quote! {
+            let #var: #ty = match __ctx.parse_arg(#name) {
+                Ok(value) => value,
+                Err(e) => {
+                    __ctx.resp.set_status(e.status());
+                    __ctx.resp.set_body(e.to_string());
+                    return Ok(());
+                }
+            };
+        });
+        vars.push(var);
+    }
+
+    Ok((ex, parses, vars))
+}
+
+fn expand(method: &str, attr: TokenStream, item: TokenStream) -> Result<TokenStream2, Error> {
+    let path: LitStr = syn::parse(attr)?;
+    let func: ItemFn = syn::parse(item)?;
+    let value = path.value();
+    if !value.starts_with('/') {
+        return Err(Error::new_spanned(&path, "route path must starts with `/`"));
+    }
+    let names = path_args(&value).map_err(|e| Error::new_spanned(&path, e))?;
+
+    let sig = &func.sig;
+    if sig.asyncness.is_none() {
+        return Err(Error::new_spanned(sig.fn_token, "route handler must be a async fn"));
+    }
+    if !sig.generics.params.is_empty() {
+        return Err(Error::new_spanned(&sig.generics, "route handler can't be generic"));
+    }
+
+    let (ex, parses, vars) = handler_args(sig, &names, &value)?;
+
+    let (attrs, vis, name) = (&func.attrs, &func.vis, &sig.ident);
+    let (docs, others): (Vec<_>, Vec<_>) =
+        attrs.iter().partition(|attr| attr.path().is_ident("doc"));
+    let method = format_ident!("{}", method);
+    let amiya = amiya_path();
+    let inner = ItemFn { attrs: others.into_iter().cloned().collect(), ..func.clone() };
+    // rules not checked by `path_args`, like where a catch-all arg can be, are checked at compile
+    // time by the same fn `routes!` uses
+    let check = quote_spanned!(path.span()=> const _: () = #amiya::middleware::check_path(#path););
+
+    Ok(quote! {
+        #check
+
+        #(#docs)*
+        #[allow(non_camel_case_types, missing_debug_implementations)]
+        #[derive(Clone, Copy)]
+        #vis struct #name;
+
+        impl #amiya::middleware::Handler<#ex> for #name {
+            fn path(&self) -> &'static str {
+                #path
+            }
+
+            fn method(&self) -> #amiya::Method {
+                #amiya::Method::#method
+            }
+        }
+
+        #[#amiya::async_trait]
+        impl #amiya::Middleware<#ex> for #name {
+            async fn handle(&self, __ctx: #amiya::Context<'_, #ex>) -> #amiya::Result {
+                #inner
+                #(#parses)*
+                #name(__ctx, #(#vars),*).await
+            }
+        }
+    })
+}
+
+macro_rules! route_attribute {
+    ($($(#[$doc: meta])* $name: ident => $method: ident),* $(,)?) => {
+        $(
+            $(#[$doc])*
+            #[proc_macro_attribute]
+            pub fn $name(attr: TokenStream, item: TokenStream) -> TokenStream {
+                expand(stringify!($method), attr, item)
+                    .unwrap_or_else(Error::into_compile_error)
+                    .into()
+            }
+        )*
+    };
+}
+
+route_attribute! {
+    /// Define a `GET` route handler, like `#[get("/users/{id}")]`.
+    get => Get,
+    /// Define a `HEAD` route handler.
+    head => Head,
+    /// Define a `POST` route handler.
+    post => Post,
+    /// Define a `PUT` route handler.
+    put => Put,
+    /// Define a `DELETE` route handler.
+    delete => Delete,
+    /// Define a `CONNECT` route handler.
+    connect => Connect,
+    /// Define a `OPTIONS` route handler.
+    options => Options,
+    /// Define a `TRACE` route handler.
+    trace => Trace,
+    /// Define a `PATCH` route handler.
+    patch => Patch,
+    /// Define a `ACL` route handler.
+    acl => Acl,
+    /// Define a `BASELINE-CONTROL` route handler.
+    baseline_control => BaselineControl,
+    /// Define a `BIND` route handler.
+    bind => Bind,
+    /// Define a `CHECKIN` route handler.
+    checkin => Checkin,
+    /// Define a `CHECKOUT` route handler.
+    checkout => Checkout,
+    /// Define a `COPY` route handler.
+    copy => Copy,
+    /// Define a `LABEL` route handler.
+    label => Label,
+    /// Define a `LINK` route handler.
+    link => Link,
+    /// Define a `LOCK` route handler.
+    lock => Lock,
+    /// Define a `MERGE` route handler.
+    merge => Merge,
+    /// Define a `MKACTIVITY` route handler.
+    mk_activity => MkActivity,
+    /// Define a `MKCALENDAR` route handler.
+    mk_calendar => MkCalendar,
+    /// Define a `MKCOL` route handler.
+    mk_col => MkCol,
+    /// Define a `MKREDIRECTREF` route handler.
+    mk_redirect_ref => MkRedirectRef,
+    /// Define a `MKWORKSPACE` route handler.
+    mk_workspace => MkWorkspace,
+    /// Define a `MOVE` route handler, named `r#move` because `move` is a keyword.
+    r#move => Move,
+    /// Define a `ORDERPATCH` route handler.
+    order_patch => OrderPatch,
+    /// Define a `PRI` route handler.
+    pri => Pri,
+    /// Define a `PROPFIND` route handler.
+    prop_find => PropFind,
+    /// Define a `PROPPATCH` route handler.
+    prop_patch => PropPatch,
+    /// Define a `REBIND` route handler.
+    rebind => Rebind,
+    /// Define a `REPORT` route handler.
+    report => Report,
+    /// Define a `SEARCH` route handler.
+    search => Search,
+    /// Define a `UNBIND` route handler.
+    unbind => Unbind,
+    /// Define a `UNCHECKOUT` route handler.
+    uncheckout => Uncheckout,
+    /// Define a `UNLINK` route handler.
+    unlink => Unlink,
+    /// Define a `UNLOCK` route handler.
+    unlock => Unlock,
+    /// Define a `UPDATE` route handler.
+    update => Update,
+    /// Define a `UPDATEREDIRECTREF` route handler.
+    update_redirect_ref => UpdateRedirectRef,
+    /// Define a `VERSION-CONTROL` route handler.
+    version_control => VersionControl,
+}
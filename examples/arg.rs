@@ -1,5 +1,5 @@
 use {
-    amiya::{m, middleware::Router, Context, Result, StatusCode},
+    amiya::{middleware::Router, Context, Result, StatusCode},
     std::convert::TryInto,
 };
 
@@ -24,7 +24,7 @@ fn main() {
     #[rustfmt::skip]
     let router = Router::new()
         .at("status")
-            .at("{status_code}").uses(m!(return_status_code)).done()
+            .at("{status_code}").uses(return_status_code).done()
         .done();
 
     let app = amiya::new().uses(router);
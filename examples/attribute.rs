@@ -0,0 +1,41 @@
+use amiya::{get, m, middleware::Router, post, Context, Result};
+
+// `#[get(...)]` converts the fn to a handler type with the same name, path arguments are parsed
+// to the types of fn arguments, it responds `400 Bad Request` if parse failed
+#[get("/users/{id:uint}")]
+async fn get_user(ctx: Context<'_, ()>, id: u64) -> Result {
+    ctx.resp.set_body(format!("Get user {}\n", id));
+    Ok(())
+}
+
+// handlers at same path are merged into one `MethodRouter`
+#[post("/users/{id:uint}")]
+async fn update_user(ctx: Context<'_, ()>, id: u64) -> Result {
+    ctx.resp.set_body(format!("Update user {}\n", id));
+    Ok(())
+}
+
+#[get("/files/{*path}")]
+async fn get_file(ctx: Context<'_, ()>, path: String) -> Result {
+    ctx.resp.set_body(format!("Get file {}\n", path));
+    Ok(())
+}
+
+fn main() {
+    #[rustfmt::skip]
+    let router = Router::new()
+        .handler(get_user)
+        .handler(update_user)
+        .handler(get_file)
+        .fallback()
+            .uses(m!(ctx => ctx.resp.set_body("Not found\n");));
+
+    print!("{}", router.routes());
+
+    // `build` panics if there are conflict routes
+    let app = amiya::new().uses(router.build());
+
+    app.listen("[::]:8080").unwrap();
+
+    std::thread::park();
+}
@@ -1,5 +1,5 @@
 use {
-    amiya::{m, middleware::Router, Context, Result, StatusCode},
+    amiya::{middleware::Router, Context, Result, StatusCode},
     serde::{Deserialize, Serialize},
     serde_json::{Map, Value},
 };
@@ -39,8 +39,8 @@ async fn parse_query_struct(mut ctx: Context<'_, ()>) -> Result {
 fn main() {
     #[rustfmt::skip]
     let router = Router::new()
-        .at("object").get(m!(parse_query_object)).done()
-        .at("struct").get(m!(parse_query_struct)).done();
+        .at("object").get(parse_query_object).done()
+        .at("struct").get(parse_query_struct).done();
 
     let app = amiya::new().uses(router);
 
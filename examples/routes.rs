@@ -0,0 +1,30 @@
+mod common;
+
+use {
+    amiya::{m, routes},
+    common::response,
+};
+
+fn main() {
+    // The same router as `examples/router.rs`, but use the `routes!` macro
+    let router = routes! {
+        // `"path" => { ... }` defines a sub router, `get => ...` in it is the endpoint
+        "api/v1/hello" => {
+            get => m!(ctx => response("Call version 1 hello API\n", ctx).await),
+        },
+        "static" => {
+            get => m!(ctx => response("We do not allow list dir\n", ctx).await),
+            // `fallback => { ... }` accepts methods, like the endpoint
+            fallback => {
+                get => m!(ctx => response(format!("Get file {}\n", ctx.path()), ctx).await),
+            },
+        },
+    };
+
+    // `build` panics if there are conflict routes
+    let app = amiya::new().uses(router.build());
+
+    app.listen("[::]:8080").unwrap();
+
+    std::thread::park();
+}
@@ -1,5 +1,5 @@
 // m is a macro to let you easily write middleware use closure like JavaScript's arrow function
-// it can also convert a async fn to a middleware use the `m!(async_func_name)` syntax.
+// async fns can be used as middleware directly, or by the `m!(async_func_name)` syntax.
 use amiya::m;
 
 fn main() {
@@ -27,7 +27,8 @@ fn main() {
         // you can run this file with `--no-default-features`, try it.
         .uses(m!(ctx =>
             ctx.resp.set_body(format!("Hello World from: {}", ctx.path()));
-        ));
+        ))
+        .executor(TokioExecutor(Runtime::new().unwrap()));
 
     // Start the task in the multi-thread executor too
     app.listen("[::]:8080").unwrap();
@@ -1,5 +1,5 @@
 use {
-    amiya::{m, middleware::Router, Context, Result, StatusCode},
+    amiya::{middleware::Router, Context, Result, StatusCode},
     serde::{Deserialize, Serialize},
     serde_json::{Map, Value},
 };
@@ -45,8 +45,8 @@ async fn parse_body_struct(mut ctx: Context<'_, ()>) -> Result {
 fn main() {
     #[rustfmt::skip]
     let router = Router::new()
-        .at("object").post(m!(parse_body_urlencoded)).done()
-        .at("struct").post(m!(parse_body_struct)).done();
+        .at("object").post(parse_body_urlencoded).done()
+        .at("struct").post(parse_body_struct).done();
 
     let app = amiya::new().uses(router);
 
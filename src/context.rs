@@ -1,27 +1,138 @@
 use {
-    crate::{Middleware, Request, Response, Result},
-    http_types::Body,
-    std::{borrow::Cow, collections::HashMap, sync::Arc},
+    crate::{
+        middleware::{fill_pattern, NameFinder},
+        Error, Middleware, Mime, Request, Response, Result, StatusCode,
+    },
+    futures_lite::AsyncReadExt,
+    http_types::{convert::DeserializeOwned, Body},
+    std::{borrow::Cow, collections::HashMap, fmt::Display, mem, str::FromStr, sync::Arc},
 };
 
+pub enum RequestBody {
+    Stream(Body),
+    Buffered(Vec<u8>, Mime),
+    Taken,
+}
+
+impl RequestBody {
+    async fn buffer(mut body: Body, limit: usize) -> (Self, Result) {
+        if body.len().is_some_and(|len| len > limit) {
+            return (Self::Stream(body), Err(payload_too_large(limit)));
+        }
+
+        let mime = body.mime().clone();
+        let mut bytes = Vec::new();
+        let mut reader = (&mut body).take((limit as u64).saturating_add(1));
+        if let Err(e) = reader.read_to_end(&mut bytes).await {
+            return (Self::put_back(bytes, body, mime), Err(e.into()));
+        }
+
+        if bytes.len() > limit {
+            (Self::put_back(bytes, body, mime), Err(payload_too_large(limit)))
+        } else {
+            (Self::Buffered(bytes, mime), Ok(()))
+        }
+    }
+
+    /// Put read `bytes` back before the unread `body`, so the full body can still be read.
+    fn put_back(bytes: Vec<u8>, body: Body, mime: Mime) -> Self {
+        let mut body = Body::from_bytes(bytes).chain(body);
+        body.set_mime(mime);
+        Self::Stream(body)
+    }
+}
+
+fn payload_too_large(limit: usize) -> Error {
+    Error::from_str(
+        StatusCode::PayloadTooLarge,
+        format!("request body is larger than the limit {limit} bytes"),
+    )
+}
+
+/// A middleware in a middleware list, with it's name if it has one.
+pub struct Entry<Ex> {
+    pub name: Option<Cow<'static, str>>,
+    pub middleware: Arc<dyn Middleware<Ex>>,
+}
+
+impl<Ex> Entry<Ex> {
+    pub fn new<M: Middleware<Ex> + 'static>(
+        name: Option<Cow<'static, str>>, middleware: M,
+    ) -> Self {
+        Self { name, middleware: Arc::new(middleware) }
+    }
+}
+
+impl<Ex> From<Arc<dyn Middleware<Ex>>> for Entry<Ex> {
+    fn from(middleware: Arc<dyn Middleware<Ex>>) -> Self {
+        Self { name: None, middleware }
+    }
+}
+
+/// Middleware not run yet, a list, then maybe the tail of outer list.
+pub struct Tail<'x, Ex> {
+    list: &'x [Entry<Ex>],
+    rest: Option<&'x Self>,
+}
+
+impl<Ex> Clone for Tail<'_, Ex> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<Ex> Copy for Tail<'_, Ex> {}
+
+impl<'x, Ex> Tail<'x, Ex> {
+    pub const fn new(list: &'x [Entry<Ex>]) -> Self {
+        Self { list, rest: None }
+    }
+
+    /// Run `list` first, then `rest`, without copy them.
+    const fn chain(list: &'x [Entry<Ex>], rest: &'x Self) -> Self {
+        Self { list, rest: Some(rest) }
+    }
+
+    fn split_first(self) -> Option<(&'x dyn Middleware<Ex>, Self)> {
+        let mut tail = self;
+        loop {
+            if let Some((first, list)) = tail.list.split_first() {
+                return Some((first.middleware.as_ref(), Self { list, rest: tail.rest }));
+            }
+            tail = *tail.rest?;
+        }
+    }
+}
+
 /// The context middleware works on.
 #[allow(missing_debug_implementations)]
 pub struct Context<'x, Ex> {
-    /// The incoming http request, without body. You can use [`Context::body`] method to get body.
+    /// The incoming http request, without body. You can use [`Context::body`] or
+    /// [`Context::body_bytes`] method to get body.
     ///
     /// [`Context::body`]: #method.body
+    /// [`Context::body_bytes`]: #method.body_bytes
     pub req: &'x Request,
     /// The output http response, you can directly edit it
     pub resp: &'x mut Response,
     /// User defined extra data
     pub ex: &'x mut Ex,
-    pub(crate) body: &'x mut Option<Body>,
+    pub(crate) body: &'x mut RequestBody,
     pub(crate) remain_path: &'x str,
+    /// The full path `remain_path` is a suffix of, it's the request path, or a normalized copy
+    /// of it made by a router.
+    pub(crate) full_path: &'x str,
     pub(crate) router_matches: &'x mut HashMap<Cow<'static, str>, String>,
-    pub(crate) tail: &'x [Arc<dyn Middleware<Ex>>],
+    pub(crate) matched_route: &'x mut String,
+    pub(crate) tail: Tail<'x, Ex>,
+    pub(crate) root: &'x [Entry<Ex>],
+    /// The matched prefix where `root` is mounted, it's empty except in a sub app of [`Mount`].
+    ///
+    /// [`Mount`]: middleware/struct.Mount.html
+    pub(crate) root_prefix: &'x str,
 }
 
-impl<'x, Ex> Context<'x, Ex>
+impl<Ex> Context<'_, Ex>
 where
     Ex: Send + Sync + 'static,
 {
@@ -39,24 +150,252 @@ where
     pub async fn next(&mut self) -> Result {
         if let Some((current, tail)) = self.tail.split_first() {
             self.tail = tail;
-            let next_ctx = Context {
-                req: self.req,
-                body: self.body,
-                resp: self.resp,
-                ex: self.ex,
-                remain_path: self.remain_path,
-                router_matches: self.router_matches,
-                tail,
-            };
-            current.handle(next_ctx).await
+            current.handle(self.reborrow()).await
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Run all inner middleware with a rewritten `req`, instead of the original one.
+    ///
+    /// This lets a middleware change the URL, method or headers seen by all inner middleware, for
+    /// method override, path rewrite or header normalization. The request seen by `self` and
+    /// outer middleware is unchanged.
+    ///
+    /// Request body is not a part of `req`, inner middleware still use [`Context::body`] to get it.
+    ///
+    /// Like [`Context::next`], only the first call of `next` or `next_with` will run inner
+    /// middleware.
+    ///
+    /// ## Path
+    ///
+    /// The [`path`] inner middleware see is recomputed from the new URL: if the new path still
+    /// starts with the prefix that already matched by outer [`Router`]s, only that prefix is
+    /// stripped, otherwise the full new path is used, and [`matched_route`] and [`arg`]s recorded
+    /// by outer routers are cleared.
+    ///
+    /// ## Examples
+    ///
+    /// ```
+    /// use amiya::{m, Method};
+    ///
+    /// // Support `X-HTTP-Method-Override` header for clients can only send GET and POST
+    /// let app = amiya::new().uses(m!(ctx => {
+    ///     let method_override = ctx.req.header("X-HTTP-Method-Override");
+    ///     if let Some(method) = method_override.and_then(|v| v.as_str().parse().ok()) {
+    ///         let mut req = ctx.req.clone();
+    ///         req.set_method(method);
+    ///         ctx.next_with(&req).await
+    ///     } else {
+    ///         ctx.next().await
+    ///     }
+    /// }));
+    /// ```
+    ///
+    /// ## Errors
+    ///
+    /// it returns inner middleware execute result.
+    ///
+    /// [`Context::body`]: #method.body
+    /// [`Context::next`]: #method.next
+    /// [`path`]: #method.path
+    /// [`matched_route`]: #method.matched_route
+    /// [`arg`]: #method.arg
+    /// [`Router`]: middleware/struct.Router.html
+    pub async fn next_with(&mut self, req: &Request) -> Result {
+        if let Some((current, tail)) = self.tail.split_first() {
+            self.tail = tail;
+            current.handle(self.reborrow_with(req)).await
         } else {
             Ok(())
         }
     }
 
-    /// Get incoming request body data. Only the first call will return `Some`.
+    /// Run `list` in onion model, then all inner middleware, the list is not copied.
+    ///
+    /// It's used by middleware which contains a list of middleware, the context itself is not
+    /// changed, like [`reborrow`].
+    ///
+    /// [`reborrow`]: #method.reborrow
+    pub(crate) async fn next_through(&mut self, list: &[Entry<Ex>]) -> Result {
+        let rest = self.tail;
+        let mut ctx = self.reborrow();
+        ctx.tail = Tail::chain(list, &rest);
+        ctx.next().await
+    }
+
+    /// Create a context with shorter lifetime from this one, so we can give it to a middleware
+    /// and still use `self` after that.
+    pub(crate) const fn reborrow(&mut self) -> Context<'_, Ex> {
+        Context {
+            req: self.req,
+            body: self.body,
+            resp: self.resp,
+            ex: self.ex,
+            remain_path: self.remain_path,
+            full_path: self.full_path,
+            router_matches: self.router_matches,
+            matched_route: self.matched_route,
+            tail: self.tail,
+            root: self.root,
+            root_prefix: self.root_prefix,
+        }
+    }
+
+    /// Like [`reborrow`], but uses a rewritten request `req`, see [`next_with`] for how remain
+    /// path is recomputed.
+    ///
+    /// [`reborrow`]: #method.reborrow
+    /// [`next_with`]: #method.next_with
+    pub(crate) fn reborrow_with<'r>(&'r mut self, req: &'r Request) -> Context<'r, Ex> {
+        let new_path = req.url().path();
+        let remain_path = self.rebase_remain_path(new_path).unwrap_or_else(|| {
+            // Outer routers matched nothing of the new path, so forget what they recorded
+            self.matched_route.clear();
+            self.router_matches.clear();
+            new_path
+        });
+        let mut ctx = self.reborrow();
+        ctx.req = req;
+        ctx.remain_path = remain_path;
+        ctx.full_path = req.url().path();
+        ctx
+    }
+
+    /// Like [`reborrow`], but the remain path is changed to `remain_path`, matched prefix is kept.
+    ///
+    /// `full_path` is a buffer to store the new full path, the request is not changed.
+    ///
+    /// [`reborrow`]: #method.reborrow
+    pub(crate) fn reborrow_with_path<'r>(
+        &'r mut self, remain_path: &str, full_path: &'r mut String,
+    ) -> Context<'r, Ex> {
+        full_path.clear();
+        full_path.push_str(self.matched_prefix());
+        let matched_len = full_path.len();
+        full_path.push_str(remain_path);
+        let mut ctx = self.reborrow();
+        ctx.full_path = full_path;
+        ctx.remain_path = &full_path[matched_len..];
+        ctx
+    }
+
+    fn rebase_remain_path<'p>(&self, new_path: &'p str) -> Option<&'p str> {
+        new_path
+            .strip_prefix(self.matched_prefix())
+            .filter(|remain| remain.is_empty() || remain.starts_with('/'))
+    }
+
+    /// Get incoming request body data as a stream.
+    ///
+    /// Only the first call will return `Some`, unless the body has been buffered by
+    /// [`Context::body_bytes`], then every call returns a new stream of the buffered data.
+    ///
+    /// [`Context::body_bytes`]: #method.body_bytes
     pub fn body(&mut self) -> Option<Body> {
-        self.body.take()
+        match mem::replace(self.body, RequestBody::Taken) {
+            RequestBody::Stream(body) => Some(body),
+            RequestBody::Buffered(bytes, mime) => {
+                let mut body = Body::from_bytes(bytes.clone());
+                body.set_mime(mime.clone());
+                *self.body = RequestBody::Buffered(bytes, mime);
+                Some(body)
+            }
+            RequestBody::Taken => None,
+        }
+    }
+
+    /// Read the whole incoming request body into memory and returns it.
+    ///
+    /// The read data is cached in context, so any number of middleware can call this method to
+    /// read the body again, and [`Context::body`] will returns the cached data too.
+    ///
+    /// ## Errors
+    ///
+    /// - A `413 Payload Too Large` error when body is longer than `limit` bytes. Nothing is
+    ///   cached in this case, and the body can still be read by [`Context::body`] as a stream.
+    /// - A `500 Internal Server Error` error when body has been taken by [`Context::body`] before.
+    /// - Errors when read body from the connection. The data already read is put back before the
+    ///   unread part, so the body is not consumed, it can be read again by this method or by
+    ///   [`Context::body`] as a stream.
+    ///
+    /// ## Examples
+    ///
+    /// ```
+    /// use amiya::m;
+    ///
+    /// let app = amiya::new()
+    ///     .uses(m!(ctx => {
+    ///         let size = ctx.body_bytes(1024 * 1024).await?.len();
+    ///         log::info!("request body size: {}", size);
+    ///         ctx.next().await
+    ///     }))
+    ///     .uses(m!(ctx => {
+    ///         let body = String::from_utf8_lossy(ctx.body_bytes(1024 * 1024).await?).into_owned();
+    ///         ctx.resp.set_body(body);
+    ///         Ok(())
+    ///     }));
+    /// ```
+    ///
+    /// [`Context::body`]: #method.body
+    pub async fn body_bytes(&mut self, limit: usize) -> Result<&[u8]> {
+        match mem::replace(self.body, RequestBody::Taken) {
+            RequestBody::Stream(body) => {
+                let (state, result) = RequestBody::buffer(body, limit).await;
+                *self.body = state;
+                result?;
+            }
+            other => *self.body = other,
+        }
+
+        match self.body {
+            RequestBody::Buffered(ref bytes, _) if bytes.len() > limit => {
+                Err(payload_too_large(limit))
+            }
+            RequestBody::Buffered(ref bytes, _) => Ok(bytes),
+            _ => Err(Error::from_str(
+                StatusCode::InternalServerError,
+                "request body has been taken as a stream",
+            )),
+        }
+    }
+
+    /// Like [`Context::body_bytes`], but returns the body as a UTF-8 string.
+    ///
+    /// ## Errors
+    ///
+    /// Errors of [`Context::body_bytes`], or a `400 Bad Request` error when body is not valid
+    /// UTF-8.
+    ///
+    /// [`Context::body_bytes`]: #method.body_bytes
+    pub async fn body_string(&mut self, limit: usize) -> Result<&str> {
+        let bytes = self.body_bytes(limit).await?;
+        std::str::from_utf8(bytes).map_err(|e| Error::new(StatusCode::BadRequest, e))
+    }
+
+    /// Like [`Context::body_bytes`], but deserialize the body as JSON.
+    ///
+    /// ## Errors
+    ///
+    /// Errors of [`Context::body_bytes`], or a `422 Unprocessable Entity` error when body is not
+    /// a valid JSON of type `T`.
+    ///
+    /// ## Examples
+    ///
+    /// ```
+    /// use {amiya::m, std::collections::HashMap};
+    ///
+    /// let app = amiya::new().uses(m!(ctx => {
+    ///     let data: HashMap<String, String> = ctx.body_json(4096).await?;
+    ///     ctx.resp.set_body(format!("{} fields", data.len()));
+    ///     Ok(())
+    /// }));
+    /// ```
+    ///
+    /// [`Context::body_bytes`]: #method.body_bytes
+    pub async fn body_json<T: DeserializeOwned>(&mut self, limit: usize) -> Result<T> {
+        let bytes = self.body_bytes(limit).await?.to_vec();
+        Body::from_bytes(bytes).into_json().await
     }
 
     /// The path the next router can match.
@@ -69,10 +408,102 @@ where
     /// [`Router`]: middleware/struct.Router.html
     /// [`examples/router.rs`]: https://github.com/7sDream/amiya/blob/master/examples/router.rs
     #[must_use]
-    pub fn path(&self) -> &str {
+    pub const fn path(&self) -> &str {
         self.remain_path
     }
 
+    /// The part of request path that already matched by [`Router`] middleware.
+    ///
+    /// It's always the request path with [`path`] removed from the end. If the path is normalized
+    /// by a router, see *[Router - Path Normalization]*, it's the normalized path instead.
+    ///
+    /// [`Router`]: middleware/struct.Router.html
+    /// [`path`]: #method.path
+    /// [Router - Path Normalization]: middleware/struct.Router.html#path-normalization
+    #[must_use]
+    pub fn matched_prefix(&self) -> &str {
+        &self.full_path[..self.full_path.len() - self.remain_path.len()]
+    }
+
+    /// The route pattern that matched by [`Router`] middleware, like `/status/{status_code}`.
+    ///
+    /// Unlike [`matched_prefix`], any item is recorded as it's `{name}` pattern instead of the
+    /// real path, so it's suitable to be used as the key of metrics and logs.
+    ///
+    /// It's kept after inner middleware finished, so outer middleware can read it after [`next`]
+    /// returns:
+    ///
+    /// ```
+    /// use amiya::{m, middleware::Router};
+    ///
+    /// #[rustfmt::skip]
+    /// let router = Router::new()
+    ///     .at("status")
+    ///         .at("{status_code}").get(m!(ctx => ctx.resp.set_body("Hello");)).done()
+    ///     .done();
+    ///
+    /// let app = amiya::new()
+    ///     .uses(m!(ctx => {
+    ///         ctx.next().await?;
+    ///         // prints "GET /status/{status_code}" for request "GET /status/404"
+    ///         log::info!("{} {}", ctx.req.method(), ctx.matched_route());
+    ///         Ok(())
+    ///     }))
+    ///     .uses(router);
+    /// ```
+    ///
+    /// [`Router`]: middleware/struct.Router.html
+    /// [`matched_prefix`]: #method.matched_prefix
+    /// [`next`]: #method.next
+    #[must_use]
+    pub fn matched_route(&self) -> &str {
+        self.matched_route
+    }
+
+    /// Build the url path of route named `name`, fill it's path arguments by `args`.
+    ///
+    /// The route is searched in the whole app, so you can build url of any route, not only those
+    /// in current [`Router`]. Route names are set by `.at("path").name("xxx")`, see
+    /// *[Router - Named Route]* for a example.
+    ///
+    /// If more than one route has the name, the one in the earliest middleware is used. Inside a
+    /// router, it's own items are preferred, then nested routers are searched in the order of
+    /// [`Amiya::routes`], so the result is always the same.
+    ///
+    /// Argument values are percent-encoded, except `/` in catch-all argument. Arguments not in the
+    /// route pattern are ignored.
+    ///
+    /// Inside a sub app of [`Mount`], only routes of the sub app are searched, and the path where
+    /// the sub app is mounted is added before the result.
+    ///
+    /// ## Errors
+    ///
+    /// A `500 Internal Server Error` error when no route named `name`, or some argument needed
+    /// by the route is not provided in `args`.
+    ///
+    /// [`Router`]: middleware/struct.Router.html
+    /// [`Mount`]: middleware/struct.Mount.html
+    /// [`Amiya::routes`]: struct.Amiya.html#method.routes
+    /// [Router - Named Route]: middleware/struct.Router.html#named-route
+    pub fn url_for<K: AsRef<str>, V: AsRef<str>>(
+        &self, name: &str, args: &[(K, V)],
+    ) -> Result<String> {
+        let pattern = NameFinder::find(name, self.root).ok_or_else(|| {
+            Error::from_str(StatusCode::InternalServerError, format!("no route named `{name}`"))
+        })?;
+
+        fill_pattern(&pattern, |key| {
+            args.iter().find(|(k, _)| k.as_ref() == key).map(|(_, v)| v.as_ref())
+        })
+        .map(|url| format!("{}{url}", self.root_prefix))
+        .map_err(|key| {
+            Error::from_str(
+                StatusCode::InternalServerError,
+                format!("missing argument `{key}` for route `{name}`"),
+            )
+        })
+    }
+
     /// The path argument of `name`.
     ///
     /// Will be set if a router's any item `{name}` is matched.
@@ -88,4 +519,31 @@ where
     pub fn arg<K: AsRef<str>>(&self, name: K) -> Option<&str> {
         self.router_matches.get(name.as_ref()).map(String::as_str)
     }
+
+    /// Parse the path argument of `name` to type `T`.
+    ///
+    /// Used by route attribute macros to pass typed path arguments to handler functions, see
+    /// [`Handler`], but you can also use it by yourself.
+    ///
+    /// ## Errors
+    ///
+    /// - `500 Internal Server Error` if there is no argument named `name`.
+    /// - `400 Bad Request` if the argument can't be parsed to `T`.
+    ///
+    /// [`Handler`]: middleware/trait.Handler.html
+    pub fn parse_arg<T>(&self, name: &str) -> Result<T>
+    where
+        T: FromStr,
+        T::Err: Display,
+    {
+        let value = self.arg(name).ok_or_else(|| {
+            Error::from_str(
+                StatusCode::InternalServerError,
+                format!("missing path argument `{name}`"),
+            )
+        })?;
+        value.parse().map_err(|e| {
+            Error::from_str(StatusCode::BadRequest, format!("invalid path argument `{name}`: {e}"))
+        })
+    }
 }
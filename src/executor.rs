@@ -21,15 +21,17 @@ pub trait Executor: Send + Sync {
     fn block_on<T>(&self, future: impl Future<Output = T>) -> T;
 }
 
+// Keep `once_cell` instead of `std::sync::LazyLock` so we do not need a newer compiler.
 #[cfg(feature = "built-in-executor")]
+#[allow(clippy::non_std_lazy_statics)]
 static BUILTIN_EXECUTOR: Lazy<AsyncExecutor<'_>> = Lazy::new(|| {
     let ex = AsyncExecutor::new();
     for n in 1..=num_cpus::get() {
         std::thread::Builder::new()
-            .name(format!("amiya-builtin-executor-{}", n))
+            .name(format!("amiya-builtin-executor-{n}"))
             .spawn(|| loop {
                 std::panic::catch_unwind(|| {
-                    async_io::block_on(BUILTIN_EXECUTOR.run(std::future::pending::<()>()))
+                    async_io::block_on(BUILTIN_EXECUTOR.run(std::future::pending::<()>()));
                 })
                 .ok();
             })
@@ -58,7 +60,7 @@ pub struct BuiltInExecutor;
 #[cfg(feature = "built-in-executor")]
 impl Executor for BuiltInExecutor {
     fn spawn<T: Send + 'static>(&self, future: impl Future<Output = T> + Send + 'static) {
-        BUILTIN_EXECUTOR.spawn(future).detach()
+        BUILTIN_EXECUTOR.spawn(future).detach();
     }
 
     fn block_on<T>(&self, future: impl Future<Output = T>) -> T {
@@ -32,7 +32,8 @@
 //! ### Request, Response and the process pipeline
 //!
 //! For every HTTP request comes to a Amiya server, the framework will create a [`Request`] struct
-//! to represent it. It's immutable in the whole request process pipeline.
+//! to represent it. It's immutable in the whole request process pipeline, but a middleware can
+//! give a rewritten copy of it to inner middleware by using [`next_with`].
 //!
 //! And a [`Response`] is created at the same time. It's a normal `200 OK` empty header empty body
 //! response at first, but it's mutable and can be edit by middleware.
@@ -76,7 +77,7 @@
 //! there is a simplest example:
 //!
 //! ```
-//! use amiya::{Context, Result, m};
+//! use amiya::{Context, Result};
 //!
 //! async fn a(mut ctx: Context<'_, ()>) -> Result {
 //!     println!("A - before");
@@ -99,7 +100,7 @@
 //!     Ok(())
 //! }
 //!
-//! let amiya = amiya::new().uses(m!(a)).uses(m!(b)).uses(m!(c));
+//! let amiya = amiya::new().uses(a).uses(b).uses(c);
 //! ```
 //!
 //! When a request in, the output will be:
@@ -157,6 +158,7 @@
 //! [`Middleware`]: middleware/trait.Middleware.html
 //! [`Context`]: struct.Context.html
 //! [`next`]: struct.Context.html#method.next
+//! [`next_with`]: struct.Context.html#method.next_with
 //! [`Result`]: type.Result.html
 //! [`Router`]: middleware/struct.Router.html
 //! [`m`]: macro.m.html
@@ -182,7 +184,24 @@ pub mod middleware;
 use {
     async_channel::{Receiver, Sender},
     async_net::TcpListener,
-    std::{collections::HashMap, io, net::ToSocketAddrs, sync::Arc},
+    context::{Entry, RequestBody, Tail},
+    middleware::{Child, Collector, Routes, Visitor},
+    std::{
+        borrow::Cow,
+        collections::HashMap,
+        fmt::{self, Debug, Formatter},
+        io,
+        net::ToSocketAddrs,
+        sync::Arc,
+    },
+};
+
+#[cfg(feature = "macros")]
+pub use amiya_macros::{
+    acl, baseline_control, bind, checkin, checkout, connect, copy, delete, get, head, label, link,
+    lock, merge, mk_activity, mk_calendar, mk_col, mk_redirect_ref, mk_workspace, options,
+    order_patch, patch, post, pri, prop_find, prop_patch, put, r#move, rebind, report, search,
+    trace, unbind, uncheckout, unlink, unlock, update, update_redirect_ref, version_control,
 };
 
 pub use {
@@ -197,9 +216,12 @@ pub use {
 pub type Result<T = ()> = http_types::Result<T>;
 
 /// The Error type of middleware result type.
+///
+/// An error returned by the outermost middleware is logged, and the client gets an empty response
+/// with the status code of the error.
 pub type Error = http_types::Error;
 
-type MiddlewareList<Ex> = Vec<Arc<dyn Middleware<Ex>>>;
+type MiddlewareList<Ex> = Vec<Entry<Ex>>;
 
 /// Create a [`Amiya`] instance with extra data type `()`.
 ///
@@ -220,16 +242,66 @@ pub fn with_ex<Ex>() -> Amiya<BuiltInExecutor, Ex> {
 /// Amiya HTTP Server.
 ///
 /// Amiya itself also implement the [`Middleware`] trait and can be added to another Amiya
-/// instance, see [`examples/subapp.rs`] for a example.
+/// instance, see [`examples/subapp.rs`] for a example. To add a instance with a different extra
+/// data type, use [`Mount`].
 ///
 /// [`Middleware`]: middleware/trait.Middleware.html
+/// [`Mount`]: middleware/struct.Mount.html
 /// [`examples/subapp.rs`]: https://github.com/7sDream/amiya/blob/master/examples/subapp.rs
-#[allow(missing_debug_implementations)]
 pub struct Amiya<Exec, Ex = ()> {
     executor: Exec,
     middleware_list: MiddlewareList<Ex>,
 }
 
+impl<Exec, Ex> Debug for Amiya<Exec, Ex> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Amiya")
+            .field("middleware_count", &self.middleware_list.len())
+            .field("middleware_names", &self.middleware_names())
+            .field("routes", &self.routes())
+            .finish_non_exhaustive()
+    }
+}
+
+impl<Exec, Ex> Amiya<Exec, Ex> {
+    /// List all routes in all [`Router`]s and sub apps of this app.
+    ///
+    /// See [`Router::routes`] for details.
+    ///
+    /// ## Examples
+    ///
+    /// ```
+    /// use amiya::{m, middleware::Router};
+    ///
+    /// let app = amiya::new()
+    ///     .uses(m!(ctx => ctx.next().await))
+    ///     .uses(Router::new().at("hello").get(m!(ctx => ctx.resp.set_body("Hello!");)).done());
+    ///
+    /// log::info!("routes:\n{}", app.routes());
+    /// ```
+    ///
+    /// [`Router`]: middleware/struct.Router.html
+    /// [`Router::routes`]: middleware/struct.Router.html#method.routes
+    #[must_use]
+    pub fn routes(&self) -> Routes {
+        Routes(Collector::collect(|visitor| {
+            for entry in &self.middleware_list {
+                visitor.child(Child::new(entry.middleware.as_ref()).transparent());
+            }
+        }))
+    }
+
+    /// Names of all middleware in execution order, `None` for middleware without a name.
+    #[must_use]
+    pub fn middleware_names(&self) -> Vec<Option<&str>> {
+        self.middleware_list.iter().map(|entry| entry.name.as_deref()).collect()
+    }
+
+    fn find(&self, name: &str) -> Option<usize> {
+        self.middleware_list.iter().position(|entry| entry.name.as_deref() == Some(name))
+    }
+}
+
 impl<Ex> Default for Amiya<BuiltInExecutor, Ex> {
     fn default() -> Self {
         Self::new()
@@ -258,7 +330,8 @@ where
     /// Add a middleware to the end, middleware will be executed as the order of be added.  
     ///
     /// You can create middleware by implement the [`Middleware`] trait
-    /// for your custom type or use the [`m`] macro to convert a async func or closure.
+    /// for your custom type, use a async fn directly, or use [`middleware::from_fn`] or the [`m`]
+    /// macro to convert a closure.
     ///
     /// ## Examples
     ///
@@ -279,12 +352,294 @@ where
     /// ```
     ///
     /// [`Middleware`]: middleware/trait.Middleware.html
+    /// [`middleware::from_fn`]: middleware/fn.from_fn.html
     /// [`m`]: macro.m.html
+    #[must_use]
     pub fn uses<M: Middleware<Ex> + 'static>(mut self, middleware: M) -> Self {
-        self.middleware_list.push(Arc::new(middleware));
+        self.middleware_list.push(Entry::new(None, middleware));
+        self
+    }
+
+    /// Add a middleware to the end like [`uses`], with a `name`, so you can find it's position by
+    /// the name later, to insert other middleware before or after it, replace or remove it.
+    ///
+    /// This lets a library or sub app builds a stack, and users can still adjust it.
+    ///
+    /// Every method which finds middleware by name has a `try_` version, it returns the app
+    /// unchanged as `Err` instead of panic.
+    ///
+    /// ## Examples
+    ///
+    /// ```
+    /// use amiya::{m, middleware::Router};
+    ///
+    /// let app = amiya::new()
+    ///     .uses_as("log", m!(ctx => ctx.next().await))
+    ///     .uses_as("auth", m!(ctx => ctx.next().await))
+    ///     .uses_as("router", Router::new());
+    ///
+    /// // in user code
+    /// let app = app
+    ///     .uses_before("auth", m!(ctx => ctx.next().await))
+    ///     .uses_after_as("auth", "session", m!(ctx => ctx.next().await))
+    ///     .replace("log", m!(ctx => ctx.next().await))
+    ///     .remove("auth");
+    ///
+    /// assert_eq!(app.middleware_names(), [Some("log"), None, Some("session"), Some("router")]);
+    ///
+    /// // the app is given back if failed
+    /// let app = app.try_remove("auth").unwrap_or_else(|app| app);
+    /// ```
+    ///
+    /// ## Panics
+    ///
+    /// When `name` is already used by another middleware in this app.
+    ///
+    /// [`uses`]: #method.uses
+    #[must_use]
+    pub fn uses_as<N, M>(self, name: N, middleware: M) -> Self
+    where
+        N: Into<Cow<'static, str>>,
+        M: Middleware<Ex> + 'static,
+    {
+        let name = name.into();
+        self.assert_unused(&name);
+        let index = self.middleware_list.len();
+        self.insert(index, Some(name), middleware)
+    }
+
+    /// Fallible version of [`uses_as`], returns the app unchanged as `Err` when `name` is already
+    /// used.
+    ///
+    /// ## Errors
+    ///
+    /// When `name` is already used by another middleware in this app.
+    ///
+    /// [`uses_as`]: #method.uses_as
+    pub fn try_uses_as<N, M>(self, name: N, middleware: M) -> std::result::Result<Self, Self>
+    where
+        N: Into<Cow<'static, str>>,
+        M: Middleware<Ex> + 'static,
+    {
+        let name = name.into();
+        if self.find(&name).is_some() {
+            return Err(self);
+        }
+        let index = self.middleware_list.len();
+        Ok(self.insert(index, Some(name), middleware))
+    }
+
+    /// Insert a middleware right before the middleware named `name`.
+    ///
+    /// ## Panics
+    ///
+    /// When there is no middleware named `name`.
+    #[must_use]
+    pub fn uses_before<M: Middleware<Ex> + 'static>(self, name: &str, middleware: M) -> Self {
+        let index = self.position(name);
+        self.insert(index, None, middleware)
+    }
+
+    /// Fallible version of [`uses_before`].
+    ///
+    /// ## Errors
+    ///
+    /// When there is no middleware named `name`, the app is returned unchanged.
+    ///
+    /// [`uses_before`]: #method.uses_before
+    pub fn try_uses_before<M: Middleware<Ex> + 'static>(
+        self, name: &str, middleware: M,
+    ) -> std::result::Result<Self, Self> {
+        match self.find(name) {
+            Some(index) => Ok(self.insert(index, None, middleware)),
+            None => Err(self),
+        }
+    }
+
+    /// Insert a middleware with name `new_name` right before the middleware named `name`.
+    ///
+    /// ## Panics
+    ///
+    /// When there is no middleware named `name`, or `new_name` is already used.
+    #[must_use]
+    pub fn uses_before_as<N, M>(self, name: &str, new_name: N, middleware: M) -> Self
+    where
+        N: Into<Cow<'static, str>>,
+        M: Middleware<Ex> + 'static,
+    {
+        let new_name = new_name.into();
+        self.assert_unused(&new_name);
+        let index = self.position(name);
+        self.insert(index, Some(new_name), middleware)
+    }
+
+    /// Fallible version of [`uses_before_as`].
+    ///
+    /// ## Errors
+    ///
+    /// When there is no middleware named `name`, or `new_name` is already used, the app is
+    /// returned unchanged.
+    ///
+    /// [`uses_before_as`]: #method.uses_before_as
+    pub fn try_uses_before_as<N, M>(
+        self, name: &str, new_name: N, middleware: M,
+    ) -> std::result::Result<Self, Self>
+    where
+        N: Into<Cow<'static, str>>,
+        M: Middleware<Ex> + 'static,
+    {
+        let new_name = new_name.into();
+        match self.find(name) {
+            Some(index) if self.find(&new_name).is_none() => {
+                Ok(self.insert(index, Some(new_name), middleware))
+            }
+            _ => Err(self),
+        }
+    }
+
+    /// Insert a middleware right after the middleware named `name`.
+    ///
+    /// ## Panics
+    ///
+    /// When there is no middleware named `name`.
+    #[must_use]
+    pub fn uses_after<M: Middleware<Ex> + 'static>(self, name: &str, middleware: M) -> Self {
+        let index = self.position(name) + 1;
+        self.insert(index, None, middleware)
+    }
+
+    /// Fallible version of [`uses_after`].
+    ///
+    /// ## Errors
+    ///
+    /// When there is no middleware named `name`, the app is returned unchanged.
+    ///
+    /// [`uses_after`]: #method.uses_after
+    pub fn try_uses_after<M: Middleware<Ex> + 'static>(
+        self, name: &str, middleware: M,
+    ) -> std::result::Result<Self, Self> {
+        match self.find(name) {
+            Some(index) => Ok(self.insert(index + 1, None, middleware)),
+            None => Err(self),
+        }
+    }
+
+    /// Insert a middleware with name `new_name` right after the middleware named `name`.
+    ///
+    /// ## Panics
+    ///
+    /// When there is no middleware named `name`, or `new_name` is already used.
+    #[must_use]
+    pub fn uses_after_as<N, M>(self, name: &str, new_name: N, middleware: M) -> Self
+    where
+        N: Into<Cow<'static, str>>,
+        M: Middleware<Ex> + 'static,
+    {
+        let new_name = new_name.into();
+        self.assert_unused(&new_name);
+        let index = self.position(name) + 1;
+        self.insert(index, Some(new_name), middleware)
+    }
+
+    /// Fallible version of [`uses_after_as`].
+    ///
+    /// ## Errors
+    ///
+    /// When there is no middleware named `name`, or `new_name` is already used, the app is
+    /// returned unchanged.
+    ///
+    /// [`uses_after_as`]: #method.uses_after_as
+    pub fn try_uses_after_as<N, M>(
+        self, name: &str, new_name: N, middleware: M,
+    ) -> std::result::Result<Self, Self>
+    where
+        N: Into<Cow<'static, str>>,
+        M: Middleware<Ex> + 'static,
+    {
+        let new_name = new_name.into();
+        match self.find(name) {
+            Some(index) if self.find(&new_name).is_none() => {
+                Ok(self.insert(index + 1, Some(new_name), middleware))
+            }
+            _ => Err(self),
+        }
+    }
+
+    /// Replace the middleware named `name` by `middleware`, it keeps the name and position.
+    ///
+    /// ## Panics
+    ///
+    /// When there is no middleware named `name`.
+    #[must_use]
+    pub fn replace<M: Middleware<Ex> + 'static>(mut self, name: &str, middleware: M) -> Self {
+        let index = self.position(name);
+        self.middleware_list[index].middleware = Arc::new(middleware);
+        self
+    }
+
+    /// Fallible version of [`replace`].
+    ///
+    /// ## Errors
+    ///
+    /// When there is no middleware named `name`, the app is returned unchanged.
+    ///
+    /// [`replace`]: #method.replace
+    pub fn try_replace<M: Middleware<Ex> + 'static>(
+        mut self, name: &str, middleware: M,
+    ) -> std::result::Result<Self, Self> {
+        match self.find(name) {
+            Some(index) => {
+                self.middleware_list[index].middleware = Arc::new(middleware);
+                Ok(self)
+            }
+            None => Err(self),
+        }
+    }
+
+    /// Remove the middleware named `name`.
+    ///
+    /// ## Panics
+    ///
+    /// When there is no middleware named `name`.
+    #[must_use]
+    pub fn remove(mut self, name: &str) -> Self {
+        let index = self.position(name);
+        self.middleware_list.remove(index);
+        self
+    }
+
+    /// Fallible version of [`remove`].
+    ///
+    /// ## Errors
+    ///
+    /// When there is no middleware named `name`, the app is returned unchanged.
+    ///
+    /// [`remove`]: #method.remove
+    pub fn try_remove(mut self, name: &str) -> std::result::Result<Self, Self> {
+        match self.find(name) {
+            Some(index) => {
+                self.middleware_list.remove(index);
+                Ok(self)
+            }
+            None => Err(self),
+        }
+    }
+
+    fn insert<M: Middleware<Ex> + 'static>(
+        mut self, index: usize, name: Option<Cow<'static, str>>, middleware: M,
+    ) -> Self {
+        self.middleware_list.insert(index, Entry::new(name, middleware));
         self
     }
 
+    fn position(&self, name: &str) -> usize {
+        self.find(name).unwrap_or_else(|| panic!("no middleware named `{}`", name))
+    }
+
+    fn assert_unused(&self, name: &str) {
+        assert!(self.find(name).is_none(), "middleware name `{}` is used more than once", name);
+    }
+
     /// Set the executor.
     ///
     /// Normal users do not need to call this method because Amiya has a built-in multi-thread
@@ -311,17 +666,31 @@ where
         let mut ex = Ex::default();
         let mut resp = Response::new(StatusCode::Ok);
         let mut router_matches = HashMap::new();
-        let mut body = Some(req.take_body());
+        let mut matched_route = String::new();
+        let mut body = RequestBody::Stream(req.take_body());
         let mut ctx = Context {
             req: &req,
             body: &mut body,
             resp: &mut resp,
             ex: &mut ex,
-            tail: &tail,
+            tail: Tail::new(&tail),
             remain_path: req.url().path(),
+            full_path: req.url().path(),
             router_matches: &mut router_matches,
+            matched_route: &mut matched_route,
+            root: &tail,
+            root_prefix: "",
         };
-        ctx.next().await?;
+        if let Err(e) = ctx.next().await {
+            log::error!(
+                "Request handle error: code = {}, type = {}, detail = {}",
+                e.status(),
+                e.type_name().unwrap_or("Unknown"),
+                e,
+            );
+            // Details of the error may be sensitive, so only the status code is sent to client
+            return Ok(Response::new(e.status()));
+        }
         Ok(resp)
     }
 
@@ -351,7 +720,7 @@ where
                         executor.spawn(async move {
                             if let Err(e) = serve.await {
                                 log::error!(
-                                    "Request handle error: code = {}, type = {}, detail = {}",
+                                    "Connection error: code = {}, type = {}, detail = {}",
                                     e.status(),
                                     e.type_name().unwrap_or("Unknown"),
                                     e,
@@ -360,7 +729,7 @@ where
                         });
                     }
                     Err(e) => {
-                        log::warn!("Accept connection failed: {:?}", e);
+                        log::warn!("Accept connection failed: {e:?}");
                     }
                 },
                 // stop signal wins
@@ -422,6 +791,10 @@ where
     /// # Errors
     ///
     /// When listen provided address and port failed.
+    ///
+    /// # Panics
+    ///
+    /// When `addr` can't be resolved to any socket address.
     pub fn listen<A: ToSocketAddrs>(self, addr: A) -> io::Result<Sender<()>> {
         let addr = addr.to_socket_addrs()?.next().unwrap();
         let listener = self.executor.block_on(TcpListener::bind(addr))?;
@@ -448,11 +821,21 @@ where
             body: ctx.body,
             resp: ctx.resp,
             ex: ctx.ex,
-            tail: &self.middleware_list[..],
+            tail: Tail::new(&self.middleware_list),
             remain_path: ctx.remain_path,
+            full_path: ctx.full_path,
             router_matches: ctx.router_matches,
+            matched_route: ctx.matched_route,
+            root: ctx.root,
+            root_prefix: ctx.root_prefix,
         };
         self_ctx.next().await?;
         ctx.next().await
     }
+
+    fn children(&self, visitor: &mut dyn Visitor) {
+        for entry in &self.middleware_list {
+            visitor.child(Child::new(entry.middleware.as_ref()).transparent());
+        }
+    }
 }
@@ -0,0 +1,249 @@
+use {
+    crate::{
+        context::{Entry, Tail},
+        middleware::{Child, Visitor},
+        Context, Error, Middleware, Result,
+    },
+    async_trait::async_trait,
+    std::{
+        fmt::{self, Debug, Formatter},
+        future::Future,
+        marker::PhantomData,
+    },
+};
+
+/// Use `middleware` only when `predicate` returns `true`, otherwise just call [`Context::next`].
+///
+/// `middleware` should call [`Context::next`] by itself to continue, as usual.
+///
+/// ## Examples
+///
+/// ```
+/// use amiya::{m, middleware::when};
+///
+/// let app = amiya::new()
+///     .uses(when(
+///         |ctx| ctx.path().starts_with("/api"),
+///         m!(ctx => {
+///             ctx.next().await?;
+///             ctx.resp.insert_header("Cache-Control", "no-store");
+///             Ok(())
+///         }),
+///     ))
+///     .uses(m!(ctx => ctx.resp.set_body("Hello");));
+/// ```
+///
+/// [`Context::next`]: ../struct.Context.html#method.next
+pub fn when<Ex, P, M>(predicate: P, middleware: M) -> When<Ex, P, M>
+where
+    P: Fn(&Context<'_, Ex>) -> bool + Send + Sync,
+    M: Middleware<Ex>,
+{
+    When { predicate, middleware, ex: PhantomData }
+}
+
+/// Run several middleware as one, in onion model, in the order they are given.
+///
+/// The [`Context::next`] of the last one continues to middleware after the chain. For middleware
+/// of different types, use [`Chain::then`].
+///
+/// ## Examples
+///
+/// ```
+/// use amiya::{m, middleware::chain};
+///
+/// let common = chain(vec![
+///     m!(ctx => { println!("log"); ctx.next().await }),
+///     m!(ctx => { ctx.resp.insert_header("Server", "Amiya"); ctx.next().await }),
+/// ]);
+///
+/// let app = amiya::new().uses(common).uses(m!(ctx => ctx.resp.set_body("Hello");));
+/// ```
+///
+/// [`Context::next`]: ../struct.Context.html#method.next
+/// [`Chain::then`]: struct.Chain.html#method.then
+pub fn chain<Ex, I, M>(middleware: I) -> Chain<Ex>
+where
+    I: IntoIterator<Item = M>,
+    M: Middleware<Ex> + 'static,
+{
+    middleware.into_iter().fold(Chain::new(), Chain::then)
+}
+
+/// Run `middleware`, if it or any inner middleware returns a `Err`, give the error to `handler`,
+/// the result of `handler` is used instead.
+///
+/// `handler` is a async function like `async fn f(err: Error, ctx: Context<'_, Ex>) -> Result`,
+/// see [`ErrorHandler`]. It gets the context to edit response, but inner middleware have run, so
+/// [`Context::next`] does nothing.
+///
+/// ## Examples
+///
+/// ```
+/// use amiya::{m, middleware::catch, Context, Error, Result, StatusCode};
+///
+/// async fn show_error(err: Error, ctx: Context<'_, ()>) -> Result {
+///     ctx.resp.set_status(err.status());
+///     ctx.resp.set_body(format!("error: {}", err));
+///     Ok(())
+/// }
+///
+/// let app = amiya::new()
+///     .uses(catch(m!(ctx => ctx.next().await), show_error))
+///     .uses(m!(ctx => Err(amiya::Error::from_str(StatusCode::Forbidden, "go away"))));
+/// ```
+///
+/// [`ErrorHandler`]: trait.ErrorHandler.html
+/// [`Context::next`]: ../struct.Context.html#method.next
+pub fn catch<Ex, M, H>(middleware: M, handler: H) -> Catch<Ex, M, H>
+where
+    M: Middleware<Ex>,
+    H: for<'x> ErrorHandler<Context<'x, Ex>>,
+{
+    Catch { middleware, handler, ex: PhantomData }
+}
+
+/// Async functions which can be used as the error handler of [`catch`].
+///
+/// Like [`MiddlewareFn`], it's implemented for all functions like
+/// `async fn f(err: Error, ctx: Context<'_, Ex>) -> Result`, with `Ctx` being the [`Context`], you
+/// do not need to implement it. Closures can't return a future borrows it's argument, so they do
+/// not implement it.
+///
+/// [`catch`]: fn.catch.html
+/// [`MiddlewareFn`]: trait.MiddlewareFn.html
+/// [`Context`]: ../struct.Context.html
+pub trait ErrorHandler<Ctx>: Send + Sync {
+    /// The future returned by this function.
+    type Future: Future<Output = Result> + Send;
+
+    /// Call this function.
+    fn call(&self, err: Error, ctx: Ctx) -> Self::Future;
+}
+
+impl<'x, Ex, F, Fut> ErrorHandler<Context<'x, Ex>> for F
+where
+    F: Fn(Error, Context<'x, Ex>) -> Fut + Send + Sync,
+    Fut: Future<Output = Result> + Send,
+{
+    type Future = Fut;
+
+    fn call(&self, err: Error, ctx: Context<'x, Ex>) -> Self::Future {
+        self(err, ctx)
+    }
+}
+
+/// Middleware returned by [`when`].
+///
+/// [`when`]: fn.when.html
+#[allow(missing_debug_implementations)]
+pub struct When<Ex, P, M> {
+    predicate: P,
+    middleware: M,
+    ex: PhantomData<fn(Ex)>,
+}
+
+#[async_trait]
+impl<Ex, P, M> Middleware<Ex> for When<Ex, P, M>
+where
+    P: Fn(&Context<'_, Ex>) -> bool + Send + Sync,
+    M: Middleware<Ex>,
+    Ex: Send + Sync + 'static,
+{
+    async fn handle(&self, mut ctx: Context<'_, Ex>) -> Result {
+        if (self.predicate)(&ctx) {
+            self.middleware.handle(ctx).await
+        } else {
+            ctx.next().await
+        }
+    }
+
+    fn children(&self, visitor: &mut dyn Visitor) {
+        visitor.child(Child::new(&self.middleware).transparent());
+    }
+}
+
+/// Middleware returned by [`chain`].
+///
+/// [`chain`]: fn.chain.html
+pub struct Chain<Ex> {
+    middleware_list: Vec<Entry<Ex>>,
+}
+
+impl<Ex> Default for Chain<Ex> {
+    fn default() -> Self {
+        Self { middleware_list: vec![] }
+    }
+}
+
+impl<Ex> Debug for Chain<Ex> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Chain").field("middleware_count", &self.middleware_list.len()).finish()
+    }
+}
+
+impl<Ex> Chain<Ex> {
+    /// Create a empty chain, it just calls [`Context::next`].
+    ///
+    /// [`Context::next`]: ../struct.Context.html#method.next
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add `middleware` to the end of chain.
+    #[must_use]
+    pub fn then<M: Middleware<Ex> + 'static>(mut self, middleware: M) -> Self {
+        self.middleware_list.push(Entry::new(None, middleware));
+        self
+    }
+}
+
+#[async_trait]
+impl<Ex> Middleware<Ex> for Chain<Ex>
+where
+    Ex: Send + Sync + 'static,
+{
+    async fn handle(&self, mut ctx: Context<'_, Ex>) -> Result {
+        ctx.next_through(&self.middleware_list).await
+    }
+
+    fn children(&self, visitor: &mut dyn Visitor) {
+        for entry in &self.middleware_list {
+            visitor.child(Child::new(entry.middleware.as_ref()).transparent());
+        }
+    }
+}
+
+/// Middleware returned by [`catch`].
+///
+/// [`catch`]: fn.catch.html
+#[allow(missing_debug_implementations)]
+pub struct Catch<Ex, M, H> {
+    middleware: M,
+    handler: H,
+    ex: PhantomData<fn(Ex)>,
+}
+
+#[async_trait]
+impl<Ex, M, H> Middleware<Ex> for Catch<Ex, M, H>
+where
+    M: Middleware<Ex>,
+    H: for<'x> ErrorHandler<Context<'x, Ex>>,
+    Ex: Send + Sync + 'static,
+{
+    async fn handle(&self, mut ctx: Context<'_, Ex>) -> Result {
+        match self.middleware.handle(ctx.reborrow()).await {
+            Ok(()) => Ok(()),
+            Err(err) => {
+                // inner middleware are finished, do not run them again in handler
+                ctx.tail = Tail::new(&[]);
+                self.handler.call(err, ctx).await
+            }
+        }
+    }
+
+    fn children(&self, visitor: &mut dyn Visitor) {
+        visitor.child(Child::new(&self.middleware).transparent());
+    }
+}
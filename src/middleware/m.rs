@@ -3,7 +3,12 @@ use {
     std::{future::Future, pin::Pin},
 };
 
-type BoxedResultFut<'x> = Pin<Box<dyn Future<Output = Result> + Send + 'x>>;
+/// The boxed future returned by closures given to [`from_fn`].
+///
+/// [`from_fn`]: fn.from_fn.html
+pub type BoxedResultFut<'x> = Pin<Box<dyn Future<Output = Result> + Send + 'x>>;
+
+type BoxedMiddlewareFn<Ex> = Box<dyn Fn(Context<'_, Ex>) -> BoxedResultFut<'_> + Send + Sync>;
 
 /// The wrapper for use async function or closure as a middleware.
 ///
@@ -17,7 +22,37 @@ pub struct M<Ex> {
     /// **Do Not** set this field by hand, use macro [`m`] instead!
     ///
     /// [`m`]: ../macro.m.html
-    pub func: Box<dyn Fn(Context<'_, Ex>) -> BoxedResultFut<'_> + Send + Sync>,
+    pub func: BoxedMiddlewareFn<Ex>,
+}
+
+/// Convert a closure which returns a boxed future to a middleware.
+///
+/// Closures can't be a middleware directly like async functions, see [`MiddlewareFn`], because
+/// the future they return can't borrow the context. Boxing the future by `Box::pin` solves this,
+/// and it can capture values, which async functions can't.
+///
+/// ## Examples
+///
+/// ```
+/// use amiya::middleware::{from_fn, Router};
+///
+/// let greeting = String::from("Hello");
+///
+/// let app = amiya::new().uses(Router::new().at("hello").get(from_fn(move |ctx| {
+///     let body = format!("{} world", greeting);
+///     Box::pin(async move {
+///         ctx.resp.set_body(body);
+///         Ok(())
+///     })
+/// })).done());
+/// ```
+///
+/// [`MiddlewareFn`]: trait.MiddlewareFn.html
+pub fn from_fn<Ex, F>(func: F) -> M<Ex>
+where
+    F: for<'x> Fn(Context<'x, Ex>) -> BoxedResultFut<'x> + Send + Sync + 'static,
+{
+    M { func: Box::new(func) }
 }
 
 #[async_trait]
@@ -30,6 +65,69 @@ where
     }
 }
 
+/// Async functions which can be used as a middleware directly, without macro [`m`].
+///
+/// It's implemented for all functions like `async fn f(ctx: Context<'_, Ex>) -> Result`, with
+/// `Ctx` being the [`Context`], you do not need to implement it. It only exists because the
+/// returned future borrows the context, which can't be written as a `Fn` bound, and [`Middleware`]
+/// is implemented for all types implement it.
+///
+/// ## Examples
+///
+/// ```
+/// use amiya::{middleware::Router, Context, Result};
+///
+/// async fn log(mut ctx: Context<'_, ()>) -> Result {
+///     println!("request to {}", ctx.path());
+///     ctx.next().await
+/// }
+///
+/// async fn hello(ctx: Context<'_, ()>) -> Result {
+///     ctx.resp.set_body("Hello world");
+///     Ok(())
+/// }
+///
+/// let app = amiya::new().uses(log).uses(Router::new().at("hello").get(hello).done());
+/// ```
+///
+/// Closures can't return a future borrows it's argument, so they do not implement it, use
+/// [`from_fn`] or macro [`m`] for closures.
+///
+/// [`from_fn`]: fn.from_fn.html
+/// [`m`]: ../macro.m.html
+/// [`Context`]: ../struct.Context.html
+/// [`Middleware`]: trait.Middleware.html
+pub trait MiddlewareFn<Ctx>: Send + Sync {
+    /// The future returned by this function.
+    type Future: Future<Output = Result> + Send;
+
+    /// Call this function.
+    fn call(&self, ctx: Ctx) -> Self::Future;
+}
+
+impl<'x, Ex, F, Fut> MiddlewareFn<Context<'x, Ex>> for F
+where
+    F: Fn(Context<'x, Ex>) -> Fut + Send + Sync,
+    Fut: Future<Output = Result> + Send,
+{
+    type Future = Fut;
+
+    fn call(&self, ctx: Context<'x, Ex>) -> Self::Future {
+        self(ctx)
+    }
+}
+
+#[async_trait]
+impl<Ex, F> Middleware<Ex> for F
+where
+    F: for<'x> MiddlewareFn<Context<'x, Ex>>,
+    Ex: Send + Sync + 'static,
+{
+    async fn handle(&self, ctx: Context<'_, Ex>) -> Result {
+        self.call(ctx).await
+    }
+}
+
 /// Writer middleware easily.
 ///
 /// It's a macro to let you easily write middleware use closure and syntax like JavaScript's
@@ -41,6 +139,9 @@ where
 ///
 /// ### Convert a async function to middleware
 ///
+/// Async functions are middleware already, see [`MiddlewareFn`], the conversion is not needed
+/// now, but still works.
+///
 /// ```
 /// # use amiya::{Context, Result, m};
 /// async fn response(mut ctx: Context<'_, ()>) -> Result {
@@ -104,6 +205,7 @@ where
 ///
 /// [`M`]: middleware/struct.M.html
 /// [`Middleware`]: middleware/trait.Middleware.html
+/// [`MiddlewareFn`]: middleware/trait.MiddlewareFn.html
 #[macro_export]
 macro_rules! m {
     // Convert a async function to middleware by function name
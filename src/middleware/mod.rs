@@ -1,16 +1,44 @@
 //! Built-in middleware.
 
+mod combinator;
 mod m;
+mod mount;
+#[cfg(feature = "openapi")]
+mod openapi;
 mod router;
+mod routes;
+mod swap;
+mod vhost;
 
 use {
-    crate::{Context, Result},
+    crate::{Context, Method, Result},
     async_trait::async_trait,
 };
 
+#[cfg(feature = "openapi")]
+pub(crate) use {router::split_args, swap::swap_count};
+pub(crate) use {
+    router::{fill_pattern, request_host},
+    routes::{Collector, ConflictFinder, NameFinder},
+};
+
+#[doc(hidden)]
+pub use router::check_path;
+
+#[cfg(feature = "openapi")]
+pub use openapi::{Documented, OpenApi, Operation, Param, ParamIn};
+
 pub use {
-    m::M,
-    router::{MethodRouter, Router, RouterSetter},
+    combinator::{catch, chain, when, Catch, Chain, ErrorHandler, When},
+    m::{from_fn, BoxedResultFut, MiddlewareFn, M},
+    mount::Mount,
+    router::{
+        DotSegments, GuardRouter, MethodRouter, Replaced, RouteConflict, Router, RouterSetter,
+        TrailingSlash,
+    },
+    routes::{Child, Labeled, Mark, RouteInfo, RouteKind, Routes, Visitor},
+    swap::Swappable,
+    vhost::VirtualHosts,
 };
 
 /// Use your custom type as a middleware by implement this trait.
@@ -25,4 +53,91 @@ pub use {
 pub trait Middleware<Ex>: Send + Sync {
     /// Your middleware handler function, it will be called when request reach this middleware
     async fn handle(&self, ctx: Context<'_, Ex>) -> Result;
+
+    /// Give middleware contained in this middleware to `visitor`, with how they are mounted,
+    /// and report routes replaced when building this middleware.
+    ///
+    /// The router tree is walked by this method, for [`Context::url_for`], [`Router::routes`],
+    /// [`Router::try_build`] and [`OpenApi`]. Only middleware which contains other middleware,
+    /// like [`Router`] and [`Amiya`], need to implement it. The default implementation visits
+    /// nothing, so this middleware is a plain handler.
+    ///
+    /// ## Examples
+    ///
+    /// ```
+    /// use amiya::{
+    ///     async_trait,
+    ///     middleware::{Child, Visitor},
+    ///     Context, Middleware, Result,
+    /// };
+    ///
+    /// /// Log time used by `inner`.
+    /// struct Timed<M>(M);
+    ///
+    /// #[async_trait]
+    /// impl<Ex: Send + Sync + 'static, M: Middleware<Ex>> Middleware<Ex> for Timed<M> {
+    ///     async fn handle(&self, ctx: Context<'_, Ex>) -> Result {
+    ///         let start = std::time::Instant::now();
+    ///         let result = self.0.handle(ctx).await;
+    ///         log::info!("time used: {:?}", start.elapsed());
+    ///         result
+    ///     }
+    ///
+    ///     fn children(&self, visitor: &mut dyn Visitor) {
+    ///         visitor.child(Child::new(&self.0));
+    ///     }
+    /// }
+    /// ```
+    ///
+    /// [`Context::url_for`]: ../struct.Context.html#method.url_for
+    /// [`Router`]: struct.Router.html
+    /// [`Router::routes`]: struct.Router.html#method.routes
+    /// [`Router::try_build`]: struct.Router.html#method.try_build
+    /// [`OpenApi`]: struct.OpenApi.html
+    /// [`Amiya`]: ../struct.Amiya.html
+    fn children(&self, _visitor: &mut dyn Visitor) {}
+}
+
+/// A middleware which knows the route it should be mounted at, add it to a router by
+/// [`Router::handler`].
+///
+/// It's usually generated by route attribute macros like [`get`] and [`post`] in the `macros`
+/// feature, which converts a async fn to a unit struct of the same name. The first argument of the
+/// fn is the [`Context`], others are path arguments parsed by [`Context::parse_arg`], so their
+/// names must be args in the path and their types must implement [`FromStr`]. All of these are
+/// checked at compile time. If parse failed, the handler responds the error, `400 Bad Request`
+/// usually, without calling the fn.
+///
+/// ## Examples
+///
+/// ```
+/// # #[cfg(feature = "macros")] {
+/// use amiya::{get, middleware::Router, Context, Result};
+///
+/// /// Get user by id.
+/// #[get("/users/{id:uint}")]
+/// async fn get_user(ctx: Context<'_, ()>, id: u64) -> Result {
+///     ctx.resp.set_body(format!("user {}", id));
+///     Ok(())
+/// }
+///
+/// let router = Router::new().handler(get_user);
+/// # }
+/// ```
+///
+/// See [`examples/attribute.rs`] for a runnable example.
+///
+/// [`Router::handler`]: struct.Router.html#method.handler
+/// [`get`]: ../attr.get.html
+/// [`post`]: ../attr.post.html
+/// [`Context`]: ../struct.Context.html
+/// [`Context::parse_arg`]: ../struct.Context.html#method.parse_arg
+/// [`FromStr`]: https://doc.rust-lang.org/std/str/trait.FromStr.html
+/// [`examples/attribute.rs`]: https://github.com/7sDream/amiya/blob/master/examples/attribute.rs
+pub trait Handler<Ex>: Middleware<Ex> {
+    /// The route path this handler should be mounted at, starts with `/`, like `/users/{id}`.
+    fn path(&self) -> &'static str;
+
+    /// The HTTP method this handler accepts.
+    fn method(&self) -> Method;
 }
@@ -0,0 +1,146 @@
+use {
+    crate::{
+        context::Tail,
+        middleware::{Child, Visitor},
+        Amiya, Context, Middleware, Result,
+    },
+    async_trait::async_trait,
+    std::fmt::{self, Debug, Formatter},
+};
+
+type Init<Ex, ParentEx> = Box<dyn Fn(&Context<'_, ParentEx>) -> Ex + Send + Sync>;
+type MapBack<Ex, ParentEx> = Box<dyn Fn(Ex, &mut ParentEx) + Send + Sync>;
+
+/// A middleware runs a sub app with extra data type `Ex` in a parent app with a different extra
+/// data type `ParentEx`.
+///
+/// A [`Amiya`] app can be used as middleware of parent app directly, only if they have the same
+/// extra data type. With `Mount`, the sub app gets it's own extra data, created by `Default` or
+/// from the parent context for every request, and optionally gives it back to parent's extra data
+/// after sub app finished.
+///
+/// Except the extra data, request, response, body, path and path args are shared with parent app
+/// as usual, and parent's inner middleware run after the sub app finished. But inside the sub
+/// app, [`Context::url_for`] can only find routes of the sub app, urls it builds start with the
+/// path where the sub app is mounted, like `/blog/posts` for route `posts` in sub app at `blog`.
+///
+/// ## Examples
+///
+/// ```
+/// use amiya::{m, middleware::{Mount, Router}};
+///
+/// #[derive(Default)]
+/// struct User {
+///     name: Option<String>,
+/// }
+///
+/// #[derive(Default)]
+/// struct Blog {
+///     author: Option<String>,
+///     views: u64,
+/// }
+///
+/// let blog = amiya::with_ex().uses(m!(ctx: Blog =>
+///     ctx.ex.views += 1;
+///     let body = format!("blog of {:?}", ctx.ex.author);
+///     ctx.resp.set_body(body);
+/// ));
+///
+/// let blog = Mount::with(blog, |ctx: &amiya::Context<'_, User>| Blog {
+///     author: ctx.ex.name.clone(),
+///     ..Blog::default()
+/// })
+/// .map_back(|blog, user| println!("{:?} viewed {} blog(s)", user.name, blog.views));
+///
+/// let app = amiya::with_ex()
+///     .uses(m!(ctx: User => {
+///         ctx.ex.name = Some("amiya".to_owned());
+///         ctx.next().await
+///     }))
+///     .uses(Router::new().at("blog").is(blog));
+/// ```
+///
+/// [`Amiya`]: ../struct.Amiya.html
+/// [`Context::url_for`]: ../struct.Context.html#method.url_for
+pub struct Mount<Exec, Ex, ParentEx> {
+    app: Amiya<Exec, Ex>,
+    init: Init<Ex, ParentEx>,
+    map_back: Option<MapBack<Ex, ParentEx>>,
+}
+
+impl<Exec, Ex, ParentEx> Debug for Mount<Exec, Ex, ParentEx> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Mount")
+            .field("app", &self.app)
+            .field("map_back", &self.map_back.is_some())
+            .finish_non_exhaustive()
+    }
+}
+
+impl<Exec, Ex, ParentEx> Mount<Exec, Ex, ParentEx> {
+    /// Mount `app`, it's extra data is created by `Default`.
+    #[must_use]
+    pub fn new(app: Amiya<Exec, Ex>) -> Self
+    where
+        Ex: Default,
+    {
+        Self { app, init: Box::new(|_| Ex::default()), map_back: None }
+    }
+
+    /// Mount `app`, it's extra data is created from parent context by `init`.
+    #[must_use]
+    pub fn with<F>(app: Amiya<Exec, Ex>, init: F) -> Self
+    where
+        F: Fn(&Context<'_, ParentEx>) -> Ex + Send + Sync + 'static,
+    {
+        Self { app, init: Box::new(init), map_back: None }
+    }
+
+    /// Give the extra data of sub app back to parent's extra data by `map_back`, it's called
+    /// when sub app finished without error.
+    #[must_use]
+    pub fn map_back<F>(mut self, map_back: F) -> Self
+    where
+        F: Fn(Ex, &mut ParentEx) + Send + Sync + 'static,
+    {
+        self.map_back = Some(Box::new(map_back));
+        self
+    }
+}
+
+#[async_trait]
+impl<Exec, Ex, ParentEx> Middleware<ParentEx> for Mount<Exec, Ex, ParentEx>
+where
+    Exec: Send + Sync,
+    Ex: Send + Sync + 'static,
+    ParentEx: Send + Sync + 'static,
+{
+    async fn handle(&self, mut ctx: Context<'_, ParentEx>) -> Result {
+        let mut ex = (self.init)(&ctx);
+        let full_path = ctx.full_path;
+        let mut sub_ctx = Context {
+            req: ctx.req,
+            body: ctx.body,
+            resp: ctx.resp,
+            ex: &mut ex,
+            tail: Tail::new(&self.app.middleware_list),
+            remain_path: ctx.remain_path,
+            full_path: ctx.full_path,
+            router_matches: ctx.router_matches,
+            matched_route: ctx.matched_route,
+            root: &self.app.middleware_list,
+            root_prefix: &full_path[..full_path.len() - ctx.remain_path.len()],
+        };
+        sub_ctx.next().await?;
+        if let Some(ref map_back) = self.map_back {
+            map_back(ex, ctx.ex);
+        }
+        ctx.next().await
+    }
+
+    fn children(&self, visitor: &mut dyn Visitor) {
+        for entry in &self.app.middleware_list {
+            visitor.child(Child::new(entry.middleware.as_ref()).transparent());
+        }
+    }
+}
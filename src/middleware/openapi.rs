@@ -0,0 +1,504 @@
+use {
+    crate::{
+        middleware::{
+            split_args, swap_count, Child, Collector, Mark, RouteInfo, RouteKind, Visitor,
+        },
+        Context, Method, Middleware, Result,
+    },
+    async_trait::async_trait,
+    http_types::mime,
+    serde_json::{json, Map, Value},
+    std::{
+        borrow::Cow,
+        sync::{Arc, Mutex, PoisonError},
+    },
+};
+
+/// Where a [`Param`] is.
+///
+/// [`Param`]: struct.Param.html
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParamIn {
+    /// A path arg, like `{id}`.
+    Path,
+    /// A query string item.
+    Query,
+    /// A request header.
+    Header,
+    /// A cookie.
+    Cookie,
+}
+
+impl ParamIn {
+    const fn as_str(self) -> &'static str {
+        match self {
+            Self::Path => "path",
+            Self::Query => "query",
+            Self::Header => "header",
+            Self::Cookie => "cookie",
+        }
+    }
+}
+
+/// A parameter of an [`Operation`].
+///
+/// Path args in route pattern are documented automatically, with schema decided by their
+/// constraint. Add a path param with the same name to override it.
+///
+/// [`Operation`]: struct.Operation.html
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Param {
+    name: String,
+    location: ParamIn,
+    description: Option<String>,
+    required: bool,
+    schema: Value,
+}
+
+impl Param {
+    /// Create a param named `name` in `location`, with a string schema.
+    ///
+    /// Path params are always required, others are not required by default.
+    pub fn new<N: Into<String>>(name: N, location: ParamIn) -> Self {
+        Self {
+            name: name.into(),
+            location,
+            description: None,
+            required: location == ParamIn::Path,
+            schema: json!({ "type": "string" }),
+        }
+    }
+
+    /// Create a path param.
+    pub fn path<N: Into<String>>(name: N) -> Self {
+        Self::new(name, ParamIn::Path)
+    }
+
+    /// Create a query param.
+    pub fn query<N: Into<String>>(name: N) -> Self {
+        Self::new(name, ParamIn::Query)
+    }
+
+    /// Create a header param.
+    pub fn header<N: Into<String>>(name: N) -> Self {
+        Self::new(name, ParamIn::Header)
+    }
+
+    /// Set description.
+    #[must_use]
+    pub fn description<D: Into<String>>(mut self, description: D) -> Self {
+        self.description.replace(description.into());
+        self
+    }
+
+    /// Mark this param as required.
+    #[must_use]
+    pub const fn required(mut self) -> Self {
+        self.required = true;
+        self
+    }
+
+    /// Set the JSON schema of this param.
+    #[must_use]
+    pub fn schema(mut self, schema: Value) -> Self {
+        self.schema = schema;
+        self
+    }
+
+    fn to_json(&self) -> Value {
+        let mut param = json!({
+            "name": self.name,
+            "in": self.location.as_str(),
+            "required": self.required,
+            "schema": self.schema,
+        });
+        if let Some(ref description) = self.description {
+            param["description"] = json!(description);
+        }
+        param
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Content {
+    mime: String,
+    schema: Value,
+}
+
+impl Content {
+    fn to_json(&self) -> Value {
+        json!({ &self.mime: { "schema": self.schema } })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct ApiResponse {
+    status: u16,
+    description: String,
+    content: Option<Content>,
+}
+
+/// OpenAPI metadata of a route, use [`Documented`] to attach it to a handler.
+///
+/// If summary is not set, the label of the route will be used, see [`Labeled`].
+///
+/// [`Documented`]: struct.Documented.html
+/// [`Labeled`]: struct.Labeled.html
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Operation {
+    id: Option<String>,
+    summary: Option<String>,
+    description: Option<String>,
+    tags: Vec<String>,
+    params: Vec<Param>,
+    request_body: Option<Content>,
+    responses: Vec<ApiResponse>,
+}
+
+impl Operation {
+    /// Create a empty operation metadata.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the unique operation id.
+    #[must_use]
+    pub fn operation_id<I: Into<String>>(mut self, id: I) -> Self {
+        self.id.replace(id.into());
+        self
+    }
+
+    /// Set a short summary.
+    #[must_use]
+    pub fn summary<S: Into<String>>(mut self, summary: S) -> Self {
+        self.summary.replace(summary.into());
+        self
+    }
+
+    /// Set a long description.
+    #[must_use]
+    pub fn description<D: Into<String>>(mut self, description: D) -> Self {
+        self.description.replace(description.into());
+        self
+    }
+
+    /// Add a tag.
+    #[must_use]
+    pub fn tag<T: Into<String>>(mut self, tag: T) -> Self {
+        self.tags.push(tag.into());
+        self
+    }
+
+    /// Add a param.
+    #[must_use]
+    pub fn param(mut self, param: Param) -> Self {
+        self.params.push(param);
+        self
+    }
+
+    /// Set request body with content type `mime` and JSON `schema`.
+    #[must_use]
+    pub fn request_body<M: Into<String>>(mut self, mime: M, schema: Value) -> Self {
+        self.request_body.replace(Content { mime: mime.into(), schema });
+        self
+    }
+
+    /// Set a JSON request body with `schema`.
+    #[must_use]
+    pub fn request_json(self, schema: Value) -> Self {
+        self.request_body(mime::JSON.essence(), schema)
+    }
+
+    /// Add a response with `status` code without body.
+    #[must_use]
+    pub fn response<D: Into<String>>(mut self, status: u16, description: D) -> Self {
+        self.responses.push(ApiResponse { status, description: description.into(), content: None });
+        self
+    }
+
+    /// Add a JSON response with `status` code and body `schema`.
+    #[must_use]
+    pub fn response_json<D: Into<String>>(
+        mut self, status: u16, description: D, schema: Value,
+    ) -> Self {
+        let content = Some(Content { mime: mime::JSON.essence().to_owned(), schema });
+        self.responses.push(ApiResponse { status, description: description.into(), content });
+        self
+    }
+}
+
+/// A middleware wrapper which attaches OpenAPI [`Operation`] metadata to `middleware`.
+///
+/// ## Examples
+///
+/// ```
+/// use amiya::{m, middleware::{Documented, Operation, Router}};
+/// use serde_json::json;
+///
+/// let get_pet = Operation::new()
+///     .summary("Get a pet")
+///     .tag("pet")
+///     .response_json(200, "The pet", json!({ "type": "object" }))
+///     .response(404, "Pet not found");
+///
+/// let router = Router::new()
+///     .at("pets/{id:uint}")
+///     .get(Documented::new(get_pet, m!(ctx => ctx.resp.set_body("{}");)))
+///     .done();
+/// ```
+///
+/// [`Operation`]: struct.Operation.html
+#[allow(missing_debug_implementations)]
+pub struct Documented<M> {
+    operation: Arc<Operation>,
+    middleware: M,
+}
+
+impl<M> Documented<M> {
+    /// Create a `middleware` documented by `operation`.
+    pub fn new(operation: Operation, middleware: M) -> Self {
+        Self { operation: Arc::new(operation), middleware }
+    }
+}
+
+#[async_trait]
+impl<M, Ex> Middleware<Ex> for Documented<M>
+where
+    M: Middleware<Ex>,
+    Ex: Send + Sync + 'static,
+{
+    async fn handle(&self, ctx: Context<'_, Ex>) -> Result {
+        self.middleware.handle(ctx).await
+    }
+
+    fn children(&self, visitor: &mut dyn Visitor) {
+        visitor.child(Child::new(&self.middleware).mark(Mark::Operation(&self.operation)));
+    }
+}
+
+/// Methods OpenAPI path item supports.
+const fn method_key(method: Method) -> Option<&'static str> {
+    Some(match method {
+        Method::Get => "get",
+        Method::Put => "put",
+        Method::Post => "post",
+        Method::Delete => "delete",
+        Method::Options => "options",
+        Method::Head => "head",
+        Method::Patch => "patch",
+        Method::Trace => "trace",
+        _ => return None,
+    })
+}
+
+fn constraint_schema(constraint: Option<&str>) -> Value {
+    match constraint {
+        None => json!({ "type": "string" }),
+        Some("int") => json!({ "type": "integer" }),
+        Some("uint") => json!({ "type": "integer", "minimum": 0 }),
+        Some("alpha") => json!({ "type": "string", "pattern": "^[A-Za-z]+$" }),
+        Some("alnum") => json!({ "type": "string", "pattern": "^[A-Za-z0-9]+$" }),
+        Some("hex") => json!({ "type": "string", "pattern": "^[0-9A-Fa-f]+$" }),
+        Some(re) => json!({ "type": "string", "pattern": format!("^(?:{re})$") }),
+    }
+}
+
+fn path_param(name: &str, constraint: Option<&str>, catch_all: bool) -> Param {
+    let param = Param::path(name).schema(constraint_schema(constraint));
+    if catch_all {
+        param.description("The rest of the path, can contain `/`.")
+    } else {
+        param
+    }
+}
+
+fn operation_json(route: &RouteInfo, args: &[(&str, Option<&str>, bool)]) -> Value {
+    let empty = Operation::default();
+    let operation = route.operation.as_deref().unwrap_or(&empty);
+    let mut op = Map::new();
+
+    if let Some(ref id) = operation.id {
+        op.insert("operationId".to_owned(), json!(id));
+    }
+    if let Some(summary) = operation.summary.as_ref().or(route.label.as_ref()) {
+        op.insert("summary".to_owned(), json!(summary));
+    }
+    if let Some(ref description) = operation.description {
+        op.insert("description".to_owned(), json!(description));
+    }
+    if !operation.tags.is_empty() {
+        op.insert("tags".to_owned(), json!(operation.tags));
+    }
+
+    let declared =
+        |name: &str| operation.params.iter().any(|p| p.location == ParamIn::Path && p.name == name);
+    let catch_all = |name: &str| args.iter().any(|&(n, _, catch_all)| catch_all && n == name);
+    let params: Vec<_> = args
+        .iter()
+        .filter(|(name, ..)| !declared(name))
+        .map(|&(name, constraint, catch_all)| path_param(name, constraint, catch_all))
+        .chain(operation.params.iter().cloned())
+        .map(|param| {
+            let mut json = param.to_json();
+            if param.location == ParamIn::Path && catch_all(&param.name) {
+                json["x-amiya-catch-all"] = json!(true);
+            }
+            json
+        })
+        .collect();
+    if !params.is_empty() {
+        op.insert("parameters".to_owned(), Value::Array(params));
+    }
+
+    if let Some(ref body) = operation.request_body {
+        op.insert("requestBody".to_owned(), json!({ "required": true, "content": body.to_json() }));
+    }
+
+    let mut responses = Map::new();
+    for response in &operation.responses {
+        let mut value = json!({ "description": response.description });
+        if let Some(ref content) = response.content {
+            value["content"] = content.to_json();
+        }
+        responses.insert(response.status.to_string(), value);
+    }
+    if responses.is_empty() {
+        responses.insert("default".to_owned(), json!({ "description": "Default response" }));
+    }
+    op.insert("responses".to_owned(), Value::Object(responses));
+
+    Value::Object(op)
+}
+
+/// Middleware serves an OpenAPI 3 JSON document generated from the router tree of the app.
+///
+/// Only routes with a HTTP method, that is, set by [`MethodRouter`] or router setter's method
+/// finisher like `get`, are included, methods which OpenAPI does not support (like WebDAV ones)
+/// are skipped. Path args are converted to path params, use [`Documented`] to add more metadata.
+///
+/// The document is generated from the top level app when first requested, then cached until
+/// any [`Swappable`] swaps it's middleware. Use `is` to mount it, so the document route itself is
+/// not included in the document. It's only sent for a `GET` request of exactly the path it's
+/// mounted at, other requests are passed to the next middleware.
+///
+/// When used in a sub app of [`Mount`], the document describes that sub app, with paths under
+/// the prefix it's mounted at.
+///
+/// A catch-all arg like `{*path}` of `files/{*path}` is documented as path param `path` of path
+/// `/files/{path}`. OpenAPI has no param can match several parts, so the param is marked by an
+/// extension `"x-amiya-catch-all": true` and has a description if it's not documented by hand.
+/// Clients should send the `/`s in it's value as is, an encoded `%2F` is kept in the value, see
+/// *[Router - Catch-all Item]*.
+///
+/// This needs the `openapi` feature.
+///
+/// ## Examples
+///
+/// ```
+/// use amiya::{m, middleware::{OpenApi, Router}};
+///
+/// #[rustfmt::skip]
+/// let app = amiya::new().uses(Router::new()
+///     .at("openapi.json").is(OpenApi::new("Pet Store", "1.0.0"))
+///     .at("pets").get(m!(ctx => ctx.resp.set_body("[]");)).done()
+/// );
+///
+/// // or generate it by hand
+/// let doc = OpenApi::new("Pet Store", "1.0.0").document(&app.routes());
+/// assert!(doc["paths"]["/pets"]["get"].is_object());
+/// ```
+///
+/// [`MethodRouter`]: struct.MethodRouter.html
+/// [`Documented`]: struct.Documented.html
+/// [`Swappable`]: struct.Swappable.html
+/// [`Mount`]: struct.Mount.html
+/// [Router - Catch-all Item]: struct.Router.html#catch-all-item
+#[allow(missing_debug_implementations)]
+pub struct OpenApi {
+    title: Cow<'static, str>,
+    version: Cow<'static, str>,
+    description: Option<Cow<'static, str>>,
+    /// The document, with [`swap_count`] and the app prefix when it's generated.
+    cache: Mutex<Option<(usize, String, String)>>,
+}
+
+impl OpenApi {
+    /// Create a OpenAPI document middleware with document `title` and API `version`.
+    pub fn new<T, V>(title: T, version: V) -> Self
+    where
+        T: Into<Cow<'static, str>>,
+        V: Into<Cow<'static, str>>,
+    {
+        Self {
+            title: title.into(),
+            version: version.into(),
+            description: None,
+            cache: Mutex::new(None),
+        }
+    }
+
+    /// Set description of the API.
+    #[must_use]
+    pub fn description<D: Into<Cow<'static, str>>>(mut self, description: D) -> Self {
+        self.description.replace(description.into());
+        self
+    }
+
+    /// Generate OpenAPI document from `routes`, see [`Amiya::routes`].
+    ///
+    /// [`Amiya::routes`]: ../struct.Amiya.html#method.routes
+    #[must_use]
+    pub fn document(&self, routes: &[RouteInfo]) -> Value {
+        let mut paths = Map::new();
+        for route in routes {
+            let key = match route.method.and_then(method_key) {
+                Some(key) if route.kind != RouteKind::Fallback => key,
+                _ => continue,
+            };
+            let (path, args) = split_args(&route.path);
+            let item = paths.entry(path).or_insert_with(|| json!({}));
+            item[key] = operation_json(route, &args);
+        }
+
+        let mut info = json!({ "title": self.title, "version": self.version });
+        if let Some(ref description) = self.description {
+            info["description"] = json!(description);
+        }
+        json!({ "openapi": "3.0.3", "info": info, "paths": paths })
+    }
+}
+
+#[async_trait]
+impl<Ex> Middleware<Ex> for OpenApi
+where
+    Ex: Send + Sync + 'static,
+{
+    async fn handle(&self, mut ctx: Context<'_, Ex>) -> Result {
+        if !ctx.remain_path.is_empty() || ctx.req.method() != Method::Get {
+            return ctx.next().await;
+        }
+
+        let swaps = swap_count();
+        let prefix = ctx.root_prefix;
+        let mut cache = self.cache.lock().unwrap_or_else(PoisonError::into_inner);
+        let doc = match *cache {
+            Some((generated, ref at, ref doc)) if generated == swaps && at == prefix => doc.clone(),
+            _ => {
+                let routes = Collector::collect(|visitor| {
+                    for entry in ctx.root {
+                        visitor
+                            .child(Child::new(entry.middleware.as_ref()).at(prefix).transparent());
+                    }
+                });
+                let doc = self.document(&routes).to_string();
+                *cache = Some((swaps, prefix.to_owned(), doc.clone()));
+                doc
+            }
+        };
+        drop(cache);
+        ctx.resp.set_body(doc);
+        ctx.resp.set_content_type(mime::JSON);
+        Ok(())
+    }
+}
@@ -0,0 +1,128 @@
+use {
+    crate::{
+        middleware::{RouteInfo, RouteKind},
+        Method,
+    },
+    std::{
+        borrow::Cow,
+        error::Error,
+        fmt::{self, Display, Formatter},
+    },
+};
+
+/// A route replaced when building a router, reported to [`Visitor::replaced`] for conflict
+/// detection.
+///
+/// [`Visitor::replaced`]: trait.Visitor.html#method.replaced
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Replaced {
+    /// The endpoint is set more than once.
+    Endpoint,
+    /// The fallback is set more than once.
+    Fallback,
+    /// The router table item of this path is set more than once.
+    Item(Cow<'static, str>),
+    /// The route name is used more than once.
+    Name(Cow<'static, str>),
+    /// The method is set more than once.
+    Method(Method),
+    /// The exact host of [`VirtualHosts`] is set more than once.
+    ///
+    /// [`VirtualHosts`]: struct.VirtualHosts.html
+    Host(Cow<'static, str>),
+}
+
+impl Replaced {
+    /// Conflict message, `prefix` is where the router is mounted.
+    #[must_use]
+    pub fn message(&self, prefix: &str) -> String {
+        let at = if prefix.is_empty() { "/" } else { prefix };
+        match self {
+            Self::Endpoint => format!("endpoint of `{at}` is set more than once"),
+            Self::Fallback => format!("fallback of `{at}` is set more than once"),
+            Self::Item(path) => format!("`{prefix}/{path}` is set more than once"),
+            Self::Name(name) => format!("route name `{name}` is used more than once in `{at}`"),
+            Self::Method(method) => format!("method {method} of `{at}` is set more than once"),
+            Self::Host(host) => format!("host `{host}` of `{at}` is set more than once"),
+        }
+    }
+}
+
+/// Replace arg names by empty string, so patterns which match same paths are equal.
+///
+/// The catch-all marker and constraints are kept, for `{id}` and `{*path}`, or `{id}` and
+/// `{id:int}` match different paths.
+fn anonymous(pattern: &str) -> String {
+    let mut result = String::with_capacity(pattern.len());
+    let mut in_name = false;
+    for c in pattern.chars() {
+        match c {
+            '{' if !in_name => in_name = true,
+            '*' if in_name && result.ends_with('{') => {}
+            ':' | '}' if in_name => in_name = false,
+            _ if in_name => continue,
+            _ => {}
+        }
+        result.push(c);
+    }
+    result
+}
+
+/// Find routes which match same paths and methods with same guard, so only one of them can be
+/// reached.
+pub fn find_ambiguous(routes: &[RouteInfo], conflicts: &mut Vec<String>) {
+    let routes: Vec<_> = routes
+        .iter()
+        .filter(|route| route.kind != RouteKind::Fallback)
+        .map(|route| (anonymous(&route.path), route))
+        .collect();
+    for (i, (pattern, route)) in routes.iter().enumerate() {
+        for (other_pattern, other) in &routes[i + 1..] {
+            let same_method = match (route.method, other.method) {
+                (Some(a), Some(b)) => a == b,
+                _ => true,
+            };
+            if pattern == other_pattern && same_method && route.guard == other.guard {
+                let method =
+                    route.method.or(other.method).map_or_else(|| "*".to_owned(), |m| m.to_string());
+                conflicts.push(if route.path == other.path {
+                    format!("{method} `{}` is defined more than once", route.path)
+                } else {
+                    format!("{method} `{}` and `{}` are ambiguous", route.path, other.path)
+                });
+            }
+        }
+    }
+}
+
+/// Error of [`Router::try_build`], contains all conflicts found in router.
+///
+/// [`Router::try_build`]: struct.Router.html#method.try_build
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RouteConflict {
+    conflicts: Vec<String>,
+}
+
+impl RouteConflict {
+    pub(crate) const fn new(conflicts: Vec<String>) -> Self {
+        Self { conflicts }
+    }
+
+    /// Messages of conflicts, one for each.
+    #[must_use]
+    pub fn conflicts(&self) -> &[String] {
+        &self.conflicts
+    }
+}
+
+impl Display for RouteConflict {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "router has {} route conflict(s):", self.conflicts.len())?;
+        for conflict in &self.conflicts {
+            write!(f, "\n  - {conflict}")?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for RouteConflict {}
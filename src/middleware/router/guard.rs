@@ -0,0 +1,221 @@
+use {
+    crate::{
+        middleware::{router::Unmatched, Child, Mark, Visitor},
+        Context, Error, Middleware, Mime, Request, Result, StatusCode,
+    },
+    async_trait::async_trait,
+    http_types::headers::{self, HeaderName},
+    std::{
+        borrow::Cow,
+        fmt::{self, Debug, Formatter},
+    },
+};
+
+type Predicate = Box<dyn Fn(&Request) -> bool + Send + Sync>;
+
+struct Candidate<Ex> {
+    guard: String,
+    predicate: Predicate,
+    middleware: Box<dyn Middleware<Ex>>,
+}
+
+/// Host of request, from `Host` header or the url, with port and trailing dot removed and in
+/// lowercase. Forwarded headers are not trusted.
+///
+/// ## Errors
+///
+/// When the request has more than one `Host` header, it's a bad request, for different servers
+/// may pick different ones.
+pub fn request_host(req: &Request) -> Result<Option<String>> {
+    let host = match req.header(headers::HOST) {
+        Some(values) if values.iter().nth(1).is_some() => {
+            return Err(Error::from_str(StatusCode::BadRequest, "more than one `Host` header"))
+        }
+        Some(values) => Some(values.last().as_str()),
+        None => req.url().host_str(),
+    };
+    let Some(host) = host else { return Ok(None) };
+    let host = if host.starts_with('[') {
+        // IPv6 address, port is after `]`
+        host.find(']').map_or(host, |end| &host[..=end])
+    } else {
+        host.rsplit_once(':').map_or(host, |(host, _)| host)
+    };
+    Ok(Some(host.trim_end_matches('.').to_ascii_lowercase()))
+}
+
+/// The middleware for request diversion by guards, like `Host`, header values, content type or
+/// any predicate on the request.
+///
+/// Candidates are checked in the order they are added, the first one whose guard matches handles
+/// the request. If none matches, the fallback handles it, or the response is set to
+/// `404 Not Found` if there is no fallback.
+///
+/// It's a plain middleware, so several candidates can share one path by using it in a router
+/// table item.
+///
+/// ## Examples
+///
+/// ```
+/// use amiya::{m, middleware::{GuardRouter, Router}};
+///
+/// #[rustfmt::skip]
+/// let router = Router::new()
+///     .at("api")
+///         .is(GuardRouter::new()
+///             .header("X-Api-Version", "2", m!(ctx => ctx.resp.set_body("api v2");))
+///             .content_type("application/json", m!(ctx => ctx.resp.set_body("api v1 json");))
+///             .when(|req| req.url().query().is_some(), m!(ctx => ctx.resp.set_body("query");))
+///             .fallback(m!(ctx => ctx.resp.set_body("api v1");)));
+///
+/// let app = amiya::new()
+///     .uses(GuardRouter::new().host("example.com", router).fallback(m!(ctx =>
+///         ctx.resp.set_status(amiya::StatusCode::MisdirectedRequest);
+///     )));
+/// ```
+///
+/// Guards are shown in [`RouteInfo::guard`], and routes with different guards at same path are
+/// not conflicts for [`Router::try_build`].
+///
+/// [`RouteInfo::guard`]: struct.RouteInfo.html#structfield.guard
+/// [`Router::try_build`]: struct.Router.html#method.try_build
+pub struct GuardRouter<Ex> {
+    candidates: Vec<Candidate<Ex>>,
+    fallback: Option<Box<dyn Middleware<Ex>>>,
+}
+
+impl<Ex> Default for GuardRouter<Ex> {
+    fn default() -> Self {
+        Self { candidates: vec![], fallback: None }
+    }
+}
+
+impl<Ex> Debug for GuardRouter<Ex> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GuardRouter")
+            .field("guards", &self.candidates.iter().map(|c| &c.guard).collect::<Vec<_>>())
+            .field("fallback", &self.fallback.is_some())
+            .finish()
+    }
+}
+
+impl<Ex> GuardRouter<Ex> {
+    /// Create a new `GuardRouter`.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn candidate<M: Middleware<Ex> + 'static>(
+        mut self, guard: String, predicate: Predicate, middleware: M,
+    ) -> Self {
+        self.candidates.push(Candidate { guard, predicate, middleware: Box::new(middleware) });
+        self
+    }
+
+    /// Use `middleware` when `predicate` returns `true` for the request.
+    ///
+    /// Predicates can not be compared, so it's guard is shown as `predicate #n`, `n` is the
+    /// position of this candidate, starts from 1.
+    #[must_use]
+    pub fn when<P, M>(self, predicate: P, middleware: M) -> Self
+    where
+        P: Fn(&Request) -> bool + Send + Sync + 'static,
+        M: Middleware<Ex> + 'static,
+    {
+        let guard = format!("predicate #{}", self.candidates.len() + 1);
+        self.candidate(guard, Box::new(predicate), middleware)
+    }
+
+    /// Use `middleware` when request host is `host`.
+    ///
+    /// Host is read from `Host` header, port and trailing dot are ignored, and compared ignoring
+    /// ASCII case. A request with more than one `Host` header matches no host.
+    #[must_use]
+    pub fn host<H: AsRef<str>, M: Middleware<Ex> + 'static>(self, host: H, middleware: M) -> Self {
+        let host = host.as_ref().trim_end_matches('.').to_ascii_lowercase();
+        let guard = format!("host = {host}");
+        let predicate = move |req: &Request| matches!(request_host(req), Ok(Some(h)) if h == host);
+        self.candidate(guard, Box::new(predicate), middleware)
+    }
+
+    /// Use `middleware` when request header `name` contains `value`.
+    ///
+    /// Header values are split by comma, parameters after `;` are removed, then compared with
+    /// `value` ignoring ASCII case. So `header("Accept", "application/json", ...)` matches
+    /// `Accept: text/html, application/json;q=0.9`.
+    #[must_use]
+    pub fn header<N, V, M>(self, name: N, value: V, middleware: M) -> Self
+    where
+        N: Into<HeaderName>,
+        V: Into<String>,
+        M: Middleware<Ex> + 'static,
+    {
+        let (name, value) = (name.into(), value.into());
+        let guard = format!("{name}: {value}");
+        let predicate = move |req: &Request| {
+            req.header(&name).is_some_and(|values| {
+                values.iter().flat_map(|v| v.as_str().split(',')).any(|item| {
+                    let item = item.split(';').next().unwrap_or_default().trim();
+                    item.eq_ignore_ascii_case(&value)
+                })
+            })
+        };
+        self.candidate(guard, Box::new(predicate), middleware)
+    }
+
+    /// Use `middleware` when essence of request content type is the same as `mime`, like
+    /// `application/json` of `application/json; charset=utf-8`.
+    ///
+    /// ## Panics
+    ///
+    /// When `mime` is a invalid `&str`.
+    #[must_use]
+    pub fn content_type<T: Into<Mime>, M: Middleware<Ex> + 'static>(
+        self, mime: T, middleware: M,
+    ) -> Self {
+        let mime = mime.into();
+        let guard = format!("content-type = {}", mime.essence());
+        let predicate = move |req: &Request| {
+            req.content_type().is_some_and(|ct| ct.essence().eq_ignore_ascii_case(mime.essence()))
+        };
+        self.candidate(guard, Box::new(predicate), middleware)
+    }
+
+    /// Use `middleware` when no guard matches.
+    #[must_use]
+    pub fn fallback<M: Middleware<Ex> + 'static>(mut self, middleware: M) -> Self {
+        self.fallback = Some(Box::new(middleware));
+        self
+    }
+}
+
+#[async_trait]
+impl<Ex> Middleware<Ex> for GuardRouter<Ex>
+where
+    Ex: Send + Sync + 'static,
+{
+    async fn handle(&self, ctx: Context<'_, Ex>) -> Result {
+        if let Some(candidate) = self.candidates.iter().find(|c| (c.predicate)(ctx.req)) {
+            return candidate.middleware.handle(ctx).await;
+        }
+
+        if let Some(ref fallback) = self.fallback {
+            return fallback.handle(ctx).await;
+        }
+
+        ctx.resp.set_status(StatusCode::NotFound);
+        ctx.resp.ext_mut().insert(Unmatched);
+        Ok(())
+    }
+
+    fn children(&self, visitor: &mut dyn Visitor) {
+        for candidate in &self.candidates {
+            let guard = Mark::Guard(Cow::Borrowed(&candidate.guard));
+            visitor.child(Child::new(candidate.middleware.as_ref()).mark(guard));
+        }
+        if let Some(ref fallback) = self.fallback {
+            visitor.child(Child::new(fallback.as_ref()));
+        }
+    }
+}
@@ -8,6 +8,7 @@ pub trait RouterLike<Ex>: Sized {
     where
         P: Into<Cow<'static, str>>,
         M: Middleware<Ex> + 'static;
+    fn set_route_name(&mut self, name: Cow<'static, str>, path: Cow<'static, str>);
 }
 
 #[doc(hidden)]
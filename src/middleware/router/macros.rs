@@ -0,0 +1,122 @@
+/// Define a [`Router`] declaratively.
+///
+/// It expands to the same structure as using the [`Router`] builder API, but without the deep
+/// `.at(...).done()` chains.
+///
+/// ## Syntax
+///
+/// The macro body is a comma separated list of entries of a router:
+///
+/// - `method => middleware`: the endpoint accepts `method` and uses `middleware`, `method` is a
+///   lowercase method name of [`MethodRouter`], like `get`, `post` or `r#move`. Several method
+///   entries make one [`MethodRouter`].
+/// - `endpoint => middleware`: the endpoint uses `middleware` for all methods.
+/// - `fallback => middleware`: the fallback uses `middleware`.
+/// - `fallback => { method => middleware, ... }`: the fallback accepts these methods.
+/// - `"path" => middleware`: a router table item uses `middleware`.
+/// - `"path" => { ... }`: a router table item uses a sub router, the block has the same syntax
+///   as the macro body.
+/// - `"path" => wrap(a, b) ...`: add scoped middleware `a` and `b` to the item, then the
+///   middleware or sub router block as above, see *[Router - Scoped Middleware]*.
+///
+/// Paths are the same as the [`at`] method, malformed paths like `user/{id` are compile errors.
+///
+/// ## Examples
+///
+/// ```
+/// use amiya::{m, routes};
+///
+/// let router = routes! {
+///     get => m!(ctx => ctx.resp.set_body("index");),
+///     "api/v1" => wrap(m!(ctx => ctx.next().await)) {
+///         "user/{id:uint}" => {
+///             get => m!(ctx => ctx.resp.set_body("get user");),
+///             delete => m!(ctx => ctx.resp.set_body("delete user");),
+///         },
+///         "files/{*path}" => m!(ctx => ctx.resp.set_body("file");),
+///     },
+///     fallback => {
+///         get => m!(ctx => ctx.resp.set_body("not found");),
+///     },
+/// };
+///
+/// let app = amiya::new().uses(router.build());
+/// ```
+///
+/// ```compile_fail
+/// use amiya::{m, routes};
+///
+/// let router = routes! {
+///     "user/{id" => m!(ctx => ctx.next().await),
+/// };
+/// # let app = amiya::new().uses(router);
+/// ```
+///
+/// [`Router`]: middleware/struct.Router.html
+/// [`MethodRouter`]: middleware/struct.MethodRouter.html
+/// [`at`]: middleware/struct.Router.html#method.at
+/// [Router - Scoped Middleware]: middleware/struct.Router.html#scoped-middleware
+#[macro_export]
+macro_rules! routes {
+    (@entries $router: ident $methods: ident) => {};
+
+    (@entries $router: ident $methods: ident
+        fallback => { $($inner: tt)* } $(, $($rest: tt)*)?
+    ) => {
+        $router = $router.fallback().uses($crate::routes!(@methods $($inner)*));
+        $crate::routes!(@entries $router $methods $($($rest)*)?);
+    };
+
+    (@entries $router: ident $methods: ident
+        fallback => $middleware: expr $(, $($rest: tt)*)?
+    ) => {
+        $router = $router.fallback().uses($middleware);
+        $crate::routes!(@entries $router $methods $($($rest)*)?);
+    };
+
+    (@entries $router: ident $methods: ident
+        endpoint => $middleware: expr $(, $($rest: tt)*)?
+    ) => {
+        $router = $router.endpoint().uses($middleware);
+        $crate::routes!(@entries $router $methods $($($rest)*)?);
+    };
+
+    (@entries $router: ident $methods: ident
+        $path: literal => $(wrap($($layer: expr),+ $(,)?))? { $($inner: tt)* } $(, $($rest: tt)*)?
+    ) => {
+        const _: () = $crate::middleware::check_path($path);
+        $router = $router.at($path) $($(.wrap($layer))+)? .is($crate::routes!($($inner)*));
+        $crate::routes!(@entries $router $methods $($($rest)*)?);
+    };
+
+    (@entries $router: ident $methods: ident
+        $path: literal => $(wrap($($layer: expr),+ $(,)?))? $middleware: expr $(, $($rest: tt)*)?
+    ) => {
+        const _: () = $crate::middleware::check_path($path);
+        $router = $router.at($path) $($(.wrap($layer))+)? .is($middleware);
+        $crate::routes!(@entries $router $methods $($($rest)*)?);
+    };
+
+    (@entries $router: ident $methods: ident
+        $method: ident => $middleware: expr $(, $($rest: tt)*)?
+    ) => {
+        $methods = Some($methods.unwrap_or_default().$method($middleware));
+        $crate::routes!(@entries $router $methods $($($rest)*)?);
+    };
+
+    (@methods $($method: ident => $middleware: expr),* $(,)?) => {
+        $crate::middleware::MethodRouter::new() $(.$method($middleware))*
+    };
+
+    ($($entries: tt)*) => {{
+        #[allow(unused_mut)]
+        let mut router = $crate::middleware::Router::new();
+        #[allow(unused_mut)]
+        let mut methods: Option<$crate::middleware::MethodRouter<_>> = None;
+        $crate::routes!(@entries router methods $($entries)*);
+        if let Some(methods) = methods {
+            router = router.endpoint().uses(methods);
+        }
+        router
+    }};
+}
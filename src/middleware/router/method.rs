@@ -1,6 +1,10 @@
 use {
-    crate::{Context, Method, Middleware, Result, StatusCode},
+    crate::{
+        middleware::{Child, Mark, Replaced, Visitor},
+        Context, Method, Middleware, Result, StatusCode,
+    },
     async_trait::async_trait,
+    http_types::{headers, Body},
     std::{
         collections::HashMap,
         fmt::{self, Debug, Formatter},
@@ -8,6 +12,7 @@ use {
     },
 };
 
+// Classic methods first, then others by alphabet order, this is also the order in `Allow` header.
 static ALL_METHODS: &[Method] = &[
     Method::Get,
     Method::Head,
@@ -18,6 +23,36 @@ static ALL_METHODS: &[Method] = &[
     Method::Options,
     Method::Trace,
     Method::Patch,
+    Method::Acl,
+    Method::BaselineControl,
+    Method::Bind,
+    Method::Checkin,
+    Method::Checkout,
+    Method::Copy,
+    Method::Label,
+    Method::Link,
+    Method::Lock,
+    Method::Merge,
+    Method::MkActivity,
+    Method::MkCalendar,
+    Method::MkCol,
+    Method::MkRedirectRef,
+    Method::MkWorkspace,
+    Method::Move,
+    Method::OrderPatch,
+    Method::Pri,
+    Method::PropFind,
+    Method::PropPatch,
+    Method::Rebind,
+    Method::Report,
+    Method::Search,
+    Method::Unbind,
+    Method::Uncheckout,
+    Method::Unlink,
+    Method::Unlock,
+    Method::Update,
+    Method::UpdateRedirectRef,
+    Method::VersionControl,
 ];
 
 #[doc(hidden)]
@@ -26,6 +61,11 @@ macro_rules! impl_method {
     ($(#[$outer:meta])*
     $func_name: ident : $method: expr => $ret: ty) => {
         $(#[$outer])*
+        #[doc = concat!(
+            "A shortcut of `self.method(", stringify!($method), ", middleware)`, see ",
+            "[`Self::method`].\n\n[`Self::method`]: #method.method",
+        )]
+        #[must_use]
         pub fn $func_name<M: Middleware<Ex> + 'static>(self, middleware: M) -> $ret {
             self.method($method, middleware)
         }
@@ -42,42 +82,45 @@ macro_rules! impl_method {
 macro_rules! impl_all_http_method {
     ($ret: ty) => {
         impl_method! {
-            /// A shortcut of `self.method(Method::Get, middleware)`, see [`Self::method`].
-            ///
-            /// [`Self::method`]: #method.method
             get: Method::Get,
-            /// A shortcut of `self.method(Method::Head, middleware)`, see [`Self::method`].
-            ///
-            /// [`Self::method`]: #method.method
             head: Method::Head,
-            /// A shortcut of `self.method(Method::Post, middleware)`, see [`Self::method`].
-            ///
-            /// [`Self::method`]: #method.method
             post: Method::Post,
-            /// A shortcut of `self.method(Method::Put, middleware)`, see [`Self::method`].
-            ///
-            /// [`Self::method`]: #method.method
             put: Method::Put,
-            /// A shortcut of `self.method(Method::Delete, middleware)`, see [`Self::method`].
-            ///
-            /// [`Self::method`]: #method.method
             delete: Method::Delete,
-            /// A shortcut of `self.method(Method::Connect, middleware)`, see [`Self::method`].
-            ///
-            /// [`Self::method`]: #method.method
             connect: Method::Connect,
-            /// A shortcut of `self.method(Method::Options, middleware)`, see [`Self::method`].
-            ///
-            /// [`Self::method`]: #method.method
             options: Method::Options,
-            /// A shortcut of `self.method(Method::Trace, middleware)`, see [`Self::method`].
-            ///
-            /// [`Self::method`]: #method.method
             trace: Method::Trace,
-            /// A shortcut of `self.method(Method::Patch, middleware)`, see [`Self::method`].
-            ///
-            /// [`Self::method`]: #method.method
             patch: Method::Patch,
+            acl: Method::Acl,
+            baseline_control: Method::BaselineControl,
+            bind: Method::Bind,
+            checkin: Method::Checkin,
+            checkout: Method::Checkout,
+            copy: Method::Copy,
+            label: Method::Label,
+            link: Method::Link,
+            lock: Method::Lock,
+            merge: Method::Merge,
+            mk_activity: Method::MkActivity,
+            mk_calendar: Method::MkCalendar,
+            mk_col: Method::MkCol,
+            mk_redirect_ref: Method::MkRedirectRef,
+            mk_workspace: Method::MkWorkspace,
+            r#move: Method::Move,
+            order_patch: Method::OrderPatch,
+            pri: Method::Pri,
+            prop_find: Method::PropFind,
+            prop_patch: Method::PropPatch,
+            rebind: Method::Rebind,
+            report: Method::Report,
+            search: Method::Search,
+            unbind: Method::Unbind,
+            uncheckout: Method::Uncheckout,
+            unlink: Method::Unlink,
+            unlock: Method::Unlock,
+            update: Method::Update,
+            update_redirect_ref: Method::UpdateRedirectRef,
+            version_control: Method::VersionControl,
             => $ret
         }
     };
@@ -86,6 +129,7 @@ macro_rules! impl_all_http_method {
 macro_rules! impl_methods {
     ($(#[$outer:meta])* $func_name: ident : $methods: expr) => {
         $(#[$outer])*
+        #[must_use]
         pub fn $func_name<M: Middleware<Ex> + 'static>(self, middleware: M) -> Self {
             self.methods($methods, middleware)
         }
@@ -108,6 +152,13 @@ macro_rules! impl_methods {
 ///     .post(m!(ctx => ctx.resp.set_body("POST method");));
 /// ```
 ///
+/// `HEAD` and `OPTIONS` requests are handled automatically if you do not set them:
+///
+/// - `HEAD` uses the `GET` handler, response body is removed but `Content-Length` is kept.
+/// - `OPTIONS` returns `204 No Content` with a `Allow` header of all supported methods.
+///
+/// The `405 Method Not Allowed` response has the `Allow` header too.
+///
 /// You can set same middleware for different methods by using [`methods`] method.
 ///
 /// ```
@@ -116,14 +167,34 @@ macro_rules! impl_methods {
 ///     .methods([Method::Get, Method::Post], m!(ctx => ctx.resp.set_body("Hello World");));
 /// ```
 ///
+/// All methods defined in [`Method`] have a shortcut, include WebDAV methods. The shortcut name
+/// is the snake case of the [`Method`] variant, like `mk_col` for `Method::MkCol`, except `move`
+/// is named `r#move` because it's a keyword:
+///
+/// ```
+/// # use amiya::{middleware::MethodRouter, m};
+/// let router = MethodRouter::new()
+///     .prop_find(m!(ctx => ctx.resp.set_body("PROPFIND method");))
+///     .mk_col(m!(ctx => ctx.resp.set_body("MKCOL method");))
+///     .r#move(m!(ctx => ctx.resp.set_body("MOVE method");));
+/// ```
+///
 /// [`methods`]: #method.methods
+/// [`Method`]: ../enum.Method.html
 pub struct MethodRouter<Ex> {
     table: HashMap<Method, Arc<dyn Middleware<Ex>>>,
+    replaced: Vec<Replaced>,
 }
 
 impl<Ex> Default for MethodRouter<Ex> {
     fn default() -> Self {
-        Self { table: HashMap::new() }
+        Self { table: HashMap::new(), replaced: vec![] }
+    }
+}
+
+impl<Ex> Clone for MethodRouter<Ex> {
+    fn clone(&self) -> Self {
+        Self { table: self.table.clone(), replaced: self.replaced.clone() }
     }
 }
 
@@ -146,29 +217,48 @@ impl<Ex> MethodRouter<Ex> {
     }
 
     /// Set given `middleware` as the handler of specific HTTP method when request hit this router.
+    #[must_use]
     pub fn method<M: Middleware<Ex> + 'static>(mut self, method: Method, middleware: M) -> Self {
-        let middleware: Arc<dyn Middleware<Ex>> = Arc::new(middleware);
-        self.table.insert(method, Arc::clone(&middleware));
+        self.insert(method, Arc::new(middleware));
         self
     }
 
     /// Set given `middleware` as the handler of several HTTP methods when request hit this router.
+    #[must_use]
     pub fn methods<H: AsRef<[Method]>, M: Middleware<Ex> + 'static>(
         mut self, methods: H, middleware: M,
     ) -> Self {
         let middleware: Arc<dyn Middleware<Ex>> = Arc::new(middleware);
         methods.as_ref().iter().for_each(|method| {
-            self.table.insert(*method, Arc::clone(&middleware));
+            self.insert(*method, Arc::clone(&middleware));
         });
         self
     }
 
+    fn insert(&mut self, method: Method, middleware: Arc<dyn Middleware<Ex>>) {
+        if self.table.insert(method, middleware).is_some() {
+            self.replaced.push(Replaced::Method(method));
+        }
+    }
+
     impl_all_http_method! { Self }
 
+    /// Value of `Allow` header, all set methods and auto supported `HEAD` and `OPTIONS`.
+    fn allow(&self) -> String {
+        let allowed = |method: &&Method| match method {
+            Method::Head => {
+                self.table.contains_key(&Method::Head) || self.table.contains_key(&Method::Get)
+            }
+            Method::Options => true,
+            method => self.table.contains_key(method),
+        };
+        ALL_METHODS.iter().filter(allowed).map(Method::as_ref).collect::<Vec<_>>().join(", ")
+    }
+
     impl_methods! {
-        /// Set given `middleware` as the handler of all HTTP method, this method is almost useless
-        /// because in this case you can use that `middleware` directly and do not need a
-        /// MethodRouter.
+        /// Set given `middleware` as the handler of all HTTP method, include WebDAV and other
+        /// extension methods. This method is almost useless because in this case you can use that
+        /// `middleware` directly and do not need a `MethodRouter`.
         all: ALL_METHODS,
     }
 }
@@ -178,13 +268,44 @@ impl<Ex> Middleware<Ex> for MethodRouter<Ex>
 where
     Ex: Send + Sync + 'static,
 {
-    async fn handle(&self, ctx: Context<'_, Ex>) -> Result {
-        if let Some(middleware) = self.table.get(&ctx.req.method()) {
-            middleware.handle(ctx).await
-        } else {
-            ctx.resp.set_status(StatusCode::MethodNotAllowed);
-            ctx.resp.take_body();
-            Ok(())
+    async fn handle(&self, mut ctx: Context<'_, Ex>) -> Result {
+        let method = ctx.req.method();
+        if let Some(middleware) = self.table.get(&method) {
+            return middleware.handle(ctx).await;
+        }
+
+        match (method, self.table.get(&Method::Get)) {
+            (Method::Head, Some(get)) => {
+                get.handle(ctx.reborrow()).await?;
+                // keep length and mime of GET response body but not it's content
+                let body = ctx.resp.take_body();
+                let mut head_body = Body::from_reader(futures_lite::io::empty(), body.len());
+                head_body.set_mime(body.mime().clone());
+                ctx.resp.set_body(head_body);
+            }
+            (Method::Options, _) => {
+                ctx.resp.set_status(StatusCode::NoContent);
+                ctx.resp.insert_header(headers::ALLOW, self.allow());
+                ctx.resp.take_body();
+            }
+            _ => {
+                ctx.resp.set_status(StatusCode::MethodNotAllowed);
+                ctx.resp.insert_header(headers::ALLOW, self.allow());
+                ctx.resp.take_body();
+            }
+        }
+
+        Ok(())
+    }
+
+    fn children(&self, visitor: &mut dyn Visitor) {
+        for replaced in &self.replaced {
+            visitor.replaced(replaced);
+        }
+        for method in ALL_METHODS {
+            if let Some(middleware) = self.table.get(method) {
+                visitor.child(Child::new(middleware.as_ref()).mark(Mark::Method(*method)));
+            }
         }
     }
 }
@@ -2,19 +2,51 @@
 
 use {
     crate::{
-        impl_router_like_pub_fn, middleware::router::like::RouterLike, Context, Middleware, Result,
-        StatusCode,
+        impl_router_like_pub_fn,
+        middleware::{
+            router::{
+                conflict::find_ambiguous,
+                like::RouterLike,
+                normalize::{Normalized, PathPolicy},
+                tree::Node,
+            },
+            Child, Collector, ConflictFinder, Handler, RouteKind, Routes, Visitor,
+        },
+        Context, Middleware, Result, StatusCode,
     },
     async_trait::async_trait,
-    std::{borrow::Cow, collections::HashMap},
+    http_types::headers::{self, Headers},
+    std::{
+        borrow::Cow,
+        collections::HashMap,
+        fmt::{self, Debug, Formatter},
+    },
 };
 
+mod conflict;
+mod guard;
 mod like;
+mod macros;
 mod method;
+mod normalize;
+mod pattern;
+mod percent;
+mod scope;
 mod set_which;
 mod setter;
+mod tree;
 
-pub use {method::MethodRouter, setter::RouterSetter};
+#[cfg(feature = "openapi")]
+pub use pattern::split_args;
+pub use {
+    conflict::{Replaced, RouteConflict},
+    guard::{request_host, GuardRouter},
+    method::MethodRouter,
+    normalize::{DotSegments, TrailingSlash},
+    pattern::check_path,
+    pattern::fill_pattern,
+    setter::RouterSetter,
+};
 
 /// The middleware for request diversion by path.
 ///
@@ -38,7 +70,7 @@ pub use {method::MethodRouter, setter::RouterSetter};
 /// request.
 ///
 /// Each table item has different path, if you set a path twice, the new one will replace the
-/// first.
+/// first. Use [`try_build`] or [`build`] to find this kind of mistake.
 ///
 /// Each path, is a full part in path when split by `/`, that is, if you set a item `"abc" =>
 /// middleware A`, the path `/abcde/somesub` will not be treated as a match. Only `/abc`, `/abc/`,
@@ -47,18 +79,124 @@ pub use {method::MethodRouter, setter::RouterSetter};
 /// You can use [`at`] method to edit router table, for example: `at("abc")` will start a router
 /// table item edit environment for sub path `/abc`.
 ///
-/// ### Any Item
+/// A path can also contains several parts, like `at("api/v1")`, which is the same as a `api` item
+/// with a sub router contains `v1` item, but without a real sub router.
 ///
-/// There is a special router item called `any`, you can set it by use `at("{arg_name}")`.
+/// ### Any Item
 ///
-/// This item will match when all router table items do not match the remain path, and remain path
-/// is not just `/`. That is, remain path have sub folder.
+/// There is a special router item called `any`, you can set it by use `at("{arg_name}")`, or use
+/// it as a part of path like `at("user/{id}")`.
 ///
-/// At this condition, the `any` item will match next sub folder, and store this folder name as a
-/// match result in  [`Context`], you can use [`Context::arg`] to get it.
+/// A any part matches any non-empty part of remain path, and store it as a match result in
+/// [`Context`], you can use [`Context::arg`] to get it.
 ///
 /// see [`examples/arg.rs`] for a example code.
 ///
+/// #### Constraint
+///
+/// A any part can has a constraint, like `{id:int}`, then it only matches when the part meets the
+/// constraint. Built-in constraints are:
+///
+/// - `int`: decimal integer, can be negative.
+/// - `uint`: decimal digits.
+/// - `alpha`: ASCII letters.
+/// - `alnum`: ASCII letters and digits.
+/// - `hex`: hexadecimal digits.
+///
+/// With the `regex` feature enabled, any other constraint is used as a regex which should match
+/// the whole part, like `{slug:[a-z0-9-]+}`. Notice a constraint can't contains `/`.
+///
+/// So we can have several any items in one router, like `at("{id:int}")` and `at("{slug}")`.
+///
+/// #### Mixed Part
+///
+/// A part can also mix text and args, like `v{version}` or `{name}.{ext}`. Two args must be
+/// separated by some text. When there are several ways to match, former args take the longest
+/// value, so `{name}.{ext}` matches `a.tar.gz` as `name = a.tar`, `ext = gz`.
+///
+/// ### Catch-all Item
+///
+/// A path can ends with a catch-all part `{*arg_name}`, like `at("files/{*path}")`. It matches
+/// all the remain path after `/files/`, include the `/`s in it, and store it as a percent-decoded
+/// string, so request `/files/a%20b/c.txt` gives [`Context::arg`] `"path"` value `a b/c.txt`.
+///
+/// An encoded slash `%2F` is not decoded in the value, so splitting the value by `/` gives the
+/// same parts as the request path. For example `/files/..%2F..%2Fetc%2Fpasswd` gives value
+/// `..%2F..%2Fetc%2Fpasswd`, which is one part, not a path goes outside of `files`. But the
+/// value is still user input, a path rewritten by [`Context::next_with`] can contain `..` parts,
+/// so set [`DotSegments::Reject`] and check the value before using it as a file path.
+///
+/// Catch-all part needs a `/` at least, so request `/files` does not match the item above, but
+/// `/files/` matches with empty value. After match, remain path will be empty, so if the item's
+/// middleware is a [`Router`], it's endpoint will be used.
+///
+/// Because catch-all part matches every remain path starts with `/`, the [`fallback`] of the
+/// router is not reachable for those paths any more.
+///
+/// ### Percent Decoding
+///
+/// Remain path is split by `/` first, then every part is percent-decoded before matching, so
+/// request `/caf%C3%A9` matches item `at("café")`, and any item values you get from
+/// [`Context::arg`] are decoded too. An encoded `%2F` is decoded after splitting, so it will not
+/// split a part, request `/user/a%2Fb` matches `user/{name}` with `name = a/b`. The only
+/// exception is catch-all values, they keep `%2F` encoded, see *[Catch-all Item]*.
+///
+/// So paths in router table should be written as plain text, not percent-encoded.
+///
+/// ### Case Insensitive
+///
+/// By default, text in path is matched case-sensitively. For legacy clients, you can use
+/// [`case_insensitive`] to let a router ignore ASCII case when comparing text, include normal
+/// parts and text in mixed parts. Arg values are kept as is in the request.
+///
+/// Like path normalization, this only works on the router you set, not it's sub routers.
+///
+/// [`Context::matched_route`] still gives the path you set in table, not the one in request.
+///
+/// ### Match Priority
+///
+/// Router table is stored as a prefix tree of path parts, a request is matched part by part, so
+/// the time is decided by path length, not the count of items.
+///
+/// When several items match the remain path, the router choose one by these rules, so a router
+/// table always gives the same result for the same request:
+///
+/// 1. For every part, normal part is tried first, then mixed parts, parts with more text first,
+///    then any parts with constraint, then any parts without constraint, and at last the
+///    catch-all part. Parts of same priority are tried by the order they are added. Only when
+///    nothing matches under a part, the next one is tried.
+/// 2. The longest match wins. For example with item `a` and `a/b`, path `/a/b/c` uses `a/b`,
+///    and `/a/c` uses `a`.
+///
+/// ### Matched Route
+///
+/// Every matched router table item or any item is recorded in [`Context`], as `/path` or
+/// `/{arg_name}`, so after routing by nested routers and sub apps,
+/// [`Context::matched_route`] gives you the full route pattern like `/status/{status_code}`.
+/// The endpoint and fallback do not add anything to it.
+///
+/// ### Named Route
+///
+/// A router table item or any item can be given a name by `.at("path").name("xxx")`, then you can
+/// use [`Context::url_for`] to build the url of it, instead of hard code it in your code.
+///
+/// ```
+/// # use amiya::{middleware::Router, m};
+/// #[rustfmt::skip]
+/// let router = Router::new()
+///     .at("status")
+///         .at("{status_code}").name("status").uses(m!(ctx => ctx.resp.set_body("Hello");)).done()
+///     .done()
+///     .at("old-status").uses(m!(ctx => {
+///         // url will be "/status/404"
+///         let url = ctx.url_for("status", &[("status_code", "404")])?;
+///         ctx.resp.set_status(amiya::StatusCode::PermanentRedirect);
+///         ctx.resp.insert_header("Location", url);
+///         Ok(())
+///     }))
+///     .done();
+/// ```
+///
 /// ### Endpoint
 ///
 /// Except router table, [`Router`] has a endpoint middleware to handler condition that no more remain
@@ -97,6 +235,91 @@ pub use {method::MethodRouter, setter::RouterSetter};
 /// So if we choose the second option, the fallback is respond to all mismatched item, sometime
 /// this is what you want, and sometime not. Make sure choose the approach meets your need.
 ///
+/// ### Fallback Bubbling
+///
+/// A nested router only uses it's own fallback, so if it has none, unmatched requests get a
+/// `404 Not Found`, the fallback of parent router is not used.
+///
+/// Call [`nested_fallback`] with `true` on a router, then it's fallback also handles requests
+/// which any nested router (or [`GuardRouter`]) in it's router table can't handle, unless a
+/// nearer router in between has a fallback, whether or not it enables this mode. The fallback
+/// sees the remain path relative to it's own level, like it matches the request directly: path
+/// args, status and headers of response are restored to the state before the nested router is
+/// called, and the response body is cleared.
+///
+/// ```
+/// # use amiya::{middleware::Router, m};
+/// #[rustfmt::skip]
+/// let router = Router::new()
+///     .nested_fallback(true)
+///     .at("api")
+///         .at("v1")
+///             .at("users").get(m!(ctx => ctx.resp.set_body("users");))
+///         .done()
+///     .done()
+///     .fallback()
+///         .uses(m!(ctx => ctx.resp.set_body(format!("{} not found", ctx.path()));));
+/// ```
+///
+/// `/api/v1/posts` is not matched by the `api/v1` router, so it goes to the fallback of top level
+/// router, with remain path `/api/v1/posts`.
+///
+/// ### Scoped Middleware
+///
+/// When editing a router table item, you can use `wrap` to add middleware which only works for
+/// requests routed into this item, like auth and logging for everything under `/admin`, without
+/// a whole sub app.
+///
+/// Scoped middleware run in onion model: in the order they are added, before the item's
+/// middleware, which is the sub router or the one set by `is`. They can call [`Context::next`]
+/// to continue, or just return to stop the request. Middleware after this router are still
+/// reachable from the inner middleware as usual.
+///
+/// ```
+/// # use amiya::{middleware::Router, m, StatusCode};
+/// #[rustfmt::skip]
+/// let router = Router::new()
+///     .at("admin")
+///         .wrap(m!(ctx => {
+///             if ctx.req.header("Authorization").is_some() {
+///                 return ctx.next().await;
+///             }
+///             ctx.resp.set_status(StatusCode::Unauthorized);
+///             Ok(())
+///         }))
+///         .at("users").get(m!(ctx => ctx.resp.set_body("users");)).done()
+///     .done();
+/// ```
+///
+/// Scoped middleware run as long as the request is routed into the item, even the sub router
+/// finally gives a `404`.
+///
+/// ### Path Normalization
+///
+/// By default, router matches the remain path as is. You can set some policies to let a router
+/// normalize the remain path before matching:
+///
+/// - [`trailing_slash`]: ignore trailing slashes, or redirect to the path without them.
+/// - [`merge_slashes`]: merge duplicate slashes, like `/a//b` to `/a/b`.
+/// - [`dot_segments`]: reject or resolve `.` and `..` parts.
+///
+/// Policies only works on the router you set, not it's sub routers, so you usually set them on
+/// the top level router.
+///
+/// If the remain path is changed by normalization, inner middleware see the normalized path in
+/// [`Context::path`] and [`Context::matched_prefix`], but the request is not copied, so
+/// `ctx.req.url()` is still the original one. Redirect use `308 Permanent Redirect` so the
+/// request method and body will be kept by client.
+///
+/// ```
+/// # use amiya::{middleware::{DotSegments, Router, TrailingSlash}, m};
+/// let router = Router::new()
+///     .trailing_slash(TrailingSlash::Redirect)
+///     .merge_slashes(true)
+///     .dot_segments(DotSegments::Resolve)
+///     .at("status").get(m!(ctx => ctx.resp.set_body("OK");)).done();
+/// ```
+///
 /// ## API Design
 ///
 /// Because router can be nest, with many many levels, we need many code, many temp vars to build
@@ -183,15 +406,70 @@ pub use {method::MethodRouter, setter::RouterSetter};
 /// new path to old router. So I recommend use [`#[rustfmt::skip]`][rustfmt::skip] to prevent
 /// `rustfmt` to format the router creating code section and indent router code by hand.
 ///
+/// ### `routes!` Macro
+///
+/// If you do not like the chain calls, the [`routes!`] macro builds the same router tree with a
+/// nested block syntax, and checks paths at compile time.
+///
+/// ### Route Attribute Macros
+///
+/// With the `macros` feature, attributes like [`get`] and [`post`] convert async fns to
+/// [`Handler`]s which know their paths, and path arguments are passed as typed fn arguments. Add
+/// them by the [`handler`] method, handlers at same path are merged into one [`MethodRouter`].
+/// Every method shortcut of [`MethodRouter`] has a attribute with the same name, like
+/// `#[prop_find(...)]` and `#[r#move(...)]`.
+///
+/// ```
+/// # #[cfg(feature = "macros")] {
+/// use amiya::{get, middleware::Router, post, Context, Result};
+///
+/// #[get("/users/{id:uint}")]
+/// async fn get_user(ctx: Context<'_, ()>, id: u64) -> Result {
+///     ctx.resp.set_body(format!("get user {}", id));
+///     Ok(())
+/// }
+///
+/// #[post("/users/{id:uint}")]
+/// async fn update_user(ctx: Context<'_, ()>, id: u64) -> Result {
+///     ctx.resp.set_body(format!("update user {}", id));
+///     Ok(())
+/// }
+///
+/// let router = Router::new().handler(get_user).handler(update_user).build();
+/// # }
+/// ```
+///
 /// ## Examples
 ///
-/// see [`examples/router.rs`], [`examples/arg.rs`] and [`examples/subapp.rs`].
+/// see [`examples/router.rs`], [`examples/routes.rs`], [`examples/attribute.rs`],
+/// [`examples/arg.rs`] and [`examples/subapp.rs`].
 ///
 /// [`Router`]: #main
 /// [`Request`]: ../struct.Request.html
 /// [`path`]: ../struct.Context.html#method.path
 /// [`Context`]: ../struct.Context.html
 /// [`Context::arg`]: ../struct.Context.html#method.arg
+/// [`Context::next_with`]: ../struct.Context.html#method.next_with
+/// [`Context::matched_prefix`]: ../struct.Context.html#method.matched_prefix
+/// [`DotSegments::Reject`]: enum.DotSegments.html#variant.Reject
+/// [Catch-all Item]: #catch-all-item
+/// [`Context::path`]: ../struct.Context.html#method.path
+/// [`Context::next`]: ../struct.Context.html#method.next
+/// [`trailing_slash`]: #method.trailing_slash
+/// [`routes!`]: ../macro.routes.html
+/// [`nested_fallback`]: #method.nested_fallback
+/// [`GuardRouter`]: struct.GuardRouter.html
+/// [`get`]: ../attr.get.html
+/// [`post`]: ../attr.post.html
+/// [`Handler`]: trait.Handler.html
+/// [`handler`]: #method.handler
+/// [`try_build`]: #method.try_build
+/// [`build`]: #method.build
+/// [`case_insensitive`]: #method.case_insensitive
+/// [`merge_slashes`]: #method.merge_slashes
+/// [`dot_segments`]: #method.dot_segments
+/// [`Context::matched_route`]: ../struct.Context.html#method.matched_route
+/// [`Context::url_for`]: ../struct.Context.html#method.url_for
 /// [`Response`]: ../struct.Response.html
 /// [`endpoint`]: #method.endpoint
 /// [`at`]: #method.at
@@ -203,48 +481,78 @@ pub use {method::MethodRouter, setter::RouterSetter};
 /// [rustfmt::skip]: https://github.com/rust-lang/rustfmt#tips
 /// [`examples/arg.rs`]: https://github.com/7sDream/amiya/blob/master/examples/arg.rs
 /// [`examples/router.rs`]: https://github.com/7sDream/amiya/blob/master/examples/router.rs
+/// [`examples/routes.rs`]: https://github.com/7sDream/amiya/blob/master/examples/routes.rs
+/// [`examples/attribute.rs`]: https://github.com/7sDream/amiya/blob/master/examples/attribute.rs
 /// [`examples/subapp.rs`]: https://github.com/7sDream/amiya/blob/master/examples/subapp.rs
-#[allow(missing_debug_implementations)]
 pub struct Router<Ex> {
     endpoint: Option<Box<dyn Middleware<Ex>>>,
     fallback: Option<Box<dyn Middleware<Ex>>>,
-    any: Option<(Cow<'static, str>, Box<dyn Middleware<Ex>>)>,
-    table: HashMap<Cow<'static, str>, Box<dyn Middleware<Ex>>>,
+    table: Node<Ex>,
+    /// Route names by their item path.
+    names: HashMap<Cow<'static, str>, Vec<Cow<'static, str>>>,
+    /// Method routers of handlers added by [`Router::handler`], by their item path, so later
+    /// handlers at same path can be merged into them.
+    handlers: HashMap<&'static str, MethodRouter<Ex>>,
+    policy: PathPolicy,
+    ignore_case: bool,
+    nested_fallback: bool,
+    replaced: Vec<Replaced>,
 }
 
+/// Response extension set by a router which finds no handler for the request, see
+/// [`Router::nested_fallback`].
+pub struct Unmatched;
+
 impl<Ex> Default for Router<Ex> {
     fn default() -> Self {
-        Self { endpoint: None, fallback: None, any: None, table: HashMap::new() }
+        Self {
+            endpoint: None,
+            fallback: None,
+            table: Node::default(),
+            names: HashMap::new(),
+            handlers: HashMap::new(),
+            policy: PathPolicy::default(),
+            ignore_case: false,
+            nested_fallback: false,
+            replaced: vec![],
+        }
+    }
+}
+
+impl<Ex> Debug for Router<Ex> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Router").field("routes", &self.routes()).finish()
     }
 }
 
 impl<Ex> RouterLike<Ex> for Router<Ex> {
     fn set_endpoint<M: Middleware<Ex> + 'static>(&mut self, middleware: M) {
-        self.endpoint.replace(Box::new(middleware));
+        self.handlers.remove("");
+        self.put(None, Box::new(middleware), false);
     }
 
     fn set_fallback<M: Middleware<Ex> + 'static>(&mut self, middleware: M) {
-        self.fallback.replace(Box::new(middleware));
+        if self.fallback.replace(Box::new(middleware)).is_some() {
+            self.replaced.push(Replaced::Fallback);
+        }
     }
 
     fn insert_to_router_table<P: Into<Cow<'static, str>>, M: Middleware<Ex> + 'static>(
         &mut self, path: P, middleware: M,
     ) {
         let path = path.into();
-        if path.starts_with('{') && path.ends_with('}') {
-            match path {
-                Cow::Owned(path) => {
-                    let key = &path[1..path.len() - 1];
-                    self.any.replace((Cow::from(key.to_string()), Box::new(middleware)));
-                }
-                Cow::Borrowed(path) => {
-                    let key = &path[1..path.len() - 1];
-                    self.any.replace((Cow::from(key), Box::new(middleware)));
-                }
+        self.handlers.remove(path.as_ref());
+        self.put(Some(path), Box::new(middleware), false);
+    }
+
+    fn set_route_name(&mut self, name: Cow<'static, str>, path: Cow<'static, str>) {
+        for names in self.names.values_mut() {
+            if let Some(pos) = names.iter().position(|n| *n == name) {
+                names.remove(pos);
+                self.replaced.push(Replaced::Name(name.clone()));
             }
-        } else {
-            self.table.insert(path, Box::new(middleware));
         }
+        self.names.entry(path).or_default().push(name);
     }
 }
 
@@ -255,49 +563,268 @@ impl<Ex> Router<Ex> {
         Self::default()
     }
 
+    /// Put `middleware` at router table item `path`, or the endpoint if `path` is `None`.
+    ///
+    /// Replacing the method router of former handlers is not a conflict when `merged`, because
+    /// they are merged into `middleware`.
+    fn put(
+        &mut self, path: Option<Cow<'static, str>>, middleware: Box<dyn Middleware<Ex>>,
+        merged: bool,
+    ) {
+        let replaced = match path {
+            None => self.endpoint.replace(middleware).map(|_| Replaced::Endpoint),
+            Some(path) => self.table.insert(&path, middleware).then_some(Replaced::Item(path)),
+        };
+        if let Some(replaced) = replaced.filter(|_| !merged) {
+            self.replaced.push(replaced);
+        }
+    }
+
     impl_router_like_pub_fn! { Ex }
-}
 
-#[async_trait]
-impl<Ex> Middleware<Ex> for Router<Ex>
-where
-    Ex: Send + Sync + 'static,
-{
-    async fn handle(&self, mut ctx: Context<'_, Ex>) -> Result<()> {
+    /// Set how to treat trailing slash of remain path, default is [`TrailingSlash::Strict`].
+    ///
+    /// See *[Path Normalization]*.
+    ///
+    /// [`TrailingSlash::Strict`]: enum.TrailingSlash.html#variant.Strict
+    /// [Path Normalization]: #path-normalization
+    #[must_use]
+    pub const fn trailing_slash(mut self, policy: TrailingSlash) -> Self {
+        self.policy.trailing_slash = policy;
+        self
+    }
+
+    /// Set whether to merge duplicate slashes in remain path, like `/a//b` to `/a/b`, default is
+    /// `false`.
+    ///
+    /// See *[Path Normalization]*.
+    ///
+    /// [Path Normalization]: #path-normalization
+    #[must_use]
+    pub const fn merge_slashes(mut self, merge: bool) -> Self {
+        self.policy.merge_slashes = merge;
+        self
+    }
+
+    /// Set how to treat `.` and `..` parts in remain path, default is [`DotSegments::Keep`].
+    ///
+    /// See *[Path Normalization]*.
+    ///
+    /// [`DotSegments::Keep`]: enum.DotSegments.html#variant.Keep
+    /// [Path Normalization]: #path-normalization
+    #[must_use]
+    pub const fn dot_segments(mut self, policy: DotSegments) -> Self {
+        self.policy.dot_segments = policy;
+        self
+    }
+
+    /// Set whether to match path text ignoring ASCII case, default is `false`.
+    ///
+    /// See *[Case Insensitive]*.
+    ///
+    /// [Case Insensitive]: #case-insensitive
+    #[must_use]
+    pub fn case_insensitive(mut self, ignore_case: bool) -> Self {
+        self.ignore_case = ignore_case;
+        self.table.fold_case(ignore_case);
+        self
+    }
+
+    /// Add a [`Handler`] at the path it knows, relative to this router. Handlers at same path are
+    /// merged into one [`MethodRouter`], and path `/` means the endpoint.
+    ///
+    /// The method router is set like by `at(path).is(...)`, so if the item is also set by other
+    /// ways, the former one is replaced and it's a conflict for [`try_build`]. Handlers added
+    /// after that start a new method router.
+    ///
+    /// Handlers are usually generated by route attribute macros, see *[Route Attribute Macros]*.
+    ///
+    /// [`Handler`]: trait.Handler.html
+    /// [`MethodRouter`]: struct.MethodRouter.html
+    /// [`try_build`]: #method.try_build
+    /// [Route Attribute Macros]: #route-attribute-macros
+    #[must_use]
+    pub fn handler<H: Handler<Ex> + 'static>(mut self, handler: H) -> Self
+    where
+        Ex: Send + Sync + 'static,
+    {
+        let path = handler.path();
+        let path = path.strip_prefix('/').unwrap_or(path);
+        // only exists when the item is still set by former handlers, other setters remove it
+        let former = self.handlers.remove(path);
+        let merged = former.is_some();
+        let methods = former.unwrap_or_default().method(handler.method(), handler);
+        let item = (!path.is_empty()).then_some(Cow::Borrowed(path));
+        self.put(item, Box::new(methods.clone()), merged);
+        self.handlers.insert(path, methods);
+        self
+    }
+
+    /// Set whether to use the fallback of this router for requests which nested routers can't
+    /// handle, default is `false`.
+    ///
+    /// See *[Fallback Bubbling]*.
+    ///
+    /// [Fallback Bubbling]: #fallback-bubbling
+    #[must_use]
+    pub const fn nested_fallback(mut self, enable: bool) -> Self {
+        self.nested_fallback = enable;
+        self
+    }
+
+    /// List all routes in this router, include all nested routers, method routers and sub apps.
+    ///
+    /// Paths are relative to this router, in the same format as [`Context::matched_route`]. The
+    /// result implements [`Display`], you can print it at startup as a route table.
+    ///
+    /// ## Examples
+    ///
+    /// ```
+    /// use amiya::{m, middleware::Router};
+    ///
+    /// #[rustfmt::skip]
+    /// let router = Router::new()
+    ///     .at("status")
+    ///         .get(m!(ctx => ctx.resp.set_body("OK");))
+    ///         .at("{code:uint}").name("status").is(m!(ctx => ctx.resp.set_body("code");))
+    ///     .done();
+    ///
+    /// assert_eq!(router.routes().len(), 2);
+    /// print!("{}", router.routes());
+    /// // GET  /status              item
+    /// // *    /status/{code:uint}  any-item   @status
+    /// ```
+    ///
+    /// [`Context::matched_route`]: ../struct.Context.html#method.matched_route
+    /// [`Display`]: https://doc.rust-lang.org/std/fmt/trait.Display.html
+    #[must_use]
+    pub fn routes(&self) -> Routes {
+        Routes(Collector::collect(|visitor| self.visit(visitor)))
+    }
+
+    /// Visit the endpoint, router table items and the fallback, see [`Middleware::children`].
+    ///
+    /// The endpoint of a sub router is the router table item it's mounted at, so it has the
+    /// kind of that item.
+    fn visit(&self, visitor: &mut dyn Visitor) {
+        for replaced in &self.replaced {
+            visitor.replaced(replaced);
+        }
+        if let Some(ref endpoint) = self.endpoint {
+            visitor.child(Child::new(endpoint.as_ref()));
+        }
+        self.table.visit("", RouteKind::Endpoint, &self.names, visitor);
+        if let Some(ref fallback) = self.fallback {
+            visitor.child(Child::new(fallback.as_ref()).kind(RouteKind::Fallback));
+        }
+    }
+
+    /// Check conflict routes in this router, include all nested routers, method routers and sub
+    /// apps, returns the router itself if there is no conflict.
+    ///
+    /// Conflicts are:
+    ///
+    /// - A endpoint, fallback, router table item, route name or method is set more than once, so
+    ///   the former one is replaced.
+    /// - Several routes match same paths with same method, like `a/b` and a `b` item in nested
+    ///   router `a`, or `{id}` and `{name}`. Only one of them can be reached, see
+    ///   *[Match Priority]*.
+    ///
+    /// Routers do not check conflicts by themselves, so call this method (or [`build`]) after
+    /// you finish building the top level router.
+    ///
+    /// ## Errors
+    ///
+    /// When any conflict is found, the error contains all of them.
+    ///
+    /// ## Examples
+    ///
+    /// ```
+    /// use amiya::{m, middleware::Router};
+    ///
+    /// #[rustfmt::skip]
+    /// let result = Router::new()
+    ///     .at("a/b").get(m!(ctx => ctx.resp.set_body("1");)).done()
+    ///     .at("a")
+    ///         .at("b").get(m!(ctx => ctx.resp.set_body("2");)).done()
+    ///     .done()
+    ///     .try_build();
+    ///
+    /// assert!(result.is_err());
+    /// ```
+    ///
+    /// [Match Priority]: #match-priority
+    /// [`build`]: #method.build
+    pub fn try_build(self) -> std::result::Result<Self, RouteConflict> {
+        let mut conflicts = vec![];
+        self.visit(&mut ConflictFinder::new("", &mut conflicts));
+        find_ambiguous(&self.routes(), &mut conflicts);
+        if conflicts.is_empty() {
+            Ok(self)
+        } else {
+            Err(RouteConflict::new(conflicts))
+        }
+    }
+
+    /// Same as [`try_build`], but panics when conflict is found.
+    ///
+    /// ## Panics
+    ///
+    /// When any conflict is found, with a message contains all of them.
+    ///
+    /// [`try_build`]: #method.try_build
+    #[must_use]
+    pub fn build(self) -> Self {
+        self.try_build().unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    async fn route(&self, mut ctx: Context<'_, Ex>) -> Result
+    where
+        Ex: Send + Sync + 'static,
+    {
         if ctx.remain_path.is_empty() {
             if let Some(ref endpoint) = self.endpoint {
                 return endpoint.handle(ctx).await;
             }
         } else {
-            let path = &ctx.remain_path[1..];
-            for (target_path, sub_router) in &self.table {
-                if path.starts_with(target_path.as_ref()) {
-                    if path.len() == target_path.len() {
-                        ctx.remain_path = "";
-                    } else if path[target_path.len()..].starts_with('/') {
-                        ctx.remain_path = &path[target_path.len()..];
-                    } else {
-                        continue;
-                    }
-                    return sub_router.handle(ctx).await;
+            if let Some(found) = self.table.find(ctx.remain_path, self.ignore_case) {
+                let (remain_path, matched_len) = (ctx.remain_path, ctx.matched_route.len());
+                // only what the fallback can see is saved, args are usually empty at the level
+                // nested fallback is enabled, so nothing is copied for them in that case
+                let saved = (self.nested_fallback && self.fallback.is_some()).then(|| {
+                    let matches =
+                        (!ctx.router_matches.is_empty()).then(|| ctx.router_matches.clone());
+                    let headers: &Headers = ctx.resp.as_ref();
+                    (matches, ctx.resp.status(), headers.clone())
+                });
+                for (k, v) in found.args {
+                    ctx.router_matches.insert(k.clone(), v.into_owned());
+                }
+                ctx.matched_route.push_str(&found.route);
+                ctx.remain_path = found.remain_path;
+                if self.fallback.is_none() {
+                    return found.handler.handle(ctx).await;
                 }
-            }
 
-            if let Some((ref k, ref any)) = self.any {
-                if !path.is_empty() && !path.starts_with('/') {
-                    let next_slash = path.find('/');
-                    #[allow(clippy::option_if_let_else)] // use same ctx as mutable
-                    let pos = if let Some(pos) = next_slash {
-                        ctx.remain_path = &path[pos..];
-                        pos
-                    } else {
-                        ctx.remain_path = "";
-                        path.len()
-                    };
-                    let value = &path[0..pos];
-                    ctx.router_matches.insert(k.clone(), value.to_string());
-                    return any.handle(ctx).await;
+                found.handler.handle(ctx.reborrow()).await?;
+                // we have a fallback, so unmatched requests of nested router stop here
+                if ctx.resp.ext_mut().remove::<Unmatched>().is_none() {
+                    return Ok(());
+                }
+                let Some((router_matches, status, headers)) = saved else {
+                    return Ok(());
+                };
+                // a nested router do not match, give it to our fallback at our level, like the
+                // nested router is never called
+                ctx.remain_path = remain_path;
+                ctx.matched_route.truncate(matched_len);
+                match router_matches {
+                    Some(router_matches) => *ctx.router_matches = router_matches,
+                    None => ctx.router_matches.clear(),
                 }
+                ctx.resp.set_status(status);
+                *AsMut::<Headers>::as_mut(ctx.resp) = headers;
+                ctx.resp.take_body();
             }
 
             if let Some(ref fallback) = self.fallback {
@@ -306,6 +833,45 @@ where
         }
 
         ctx.resp.set_status(StatusCode::NotFound);
+        ctx.resp.ext_mut().insert(Unmatched);
         Ok(())
     }
 }
+
+#[async_trait]
+impl<Ex> Middleware<Ex> for Router<Ex>
+where
+    Ex: Send + Sync + 'static,
+{
+    async fn handle(&self, mut ctx: Context<'_, Ex>) -> Result<()> {
+        let at_root = ctx.matched_prefix().is_empty();
+        match self.policy.normalize(ctx.remain_path, at_root) {
+            Normalized::Keep => self.route(ctx).await,
+            Normalized::Reject => {
+                ctx.resp.set_status(StatusCode::BadRequest);
+                Ok(())
+            }
+            Normalized::Redirect(path) => {
+                let location = format!("{}{}", ctx.matched_prefix(), path);
+                // `//host/path` is a network-path reference, collapse leading slashes so we never
+                // redirect to another host
+                let mut location = format!("/{}", location.trim_start_matches('/'));
+                if let Some(query) = ctx.req.url().query() {
+                    location.push('?');
+                    location.push_str(query);
+                }
+                ctx.resp.set_status(StatusCode::PermanentRedirect);
+                ctx.resp.insert_header(headers::LOCATION, location);
+                Ok(())
+            }
+            Normalized::Rewrite(path) => {
+                let mut full_path = String::new();
+                self.route(ctx.reborrow_with_path(&path, &mut full_path)).await
+            }
+        }
+    }
+
+    fn children(&self, visitor: &mut dyn Visitor) {
+        self.visit(visitor);
+    }
+}
@@ -0,0 +1,137 @@
+use {crate::middleware::router::percent, std::borrow::Cow};
+
+/// How [`Router`] treats the trailing slash of remain path.
+///
+/// [`Router`]: struct.Router.html
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum TrailingSlash {
+    /// Keep it as is, so `/status` and `/status/` are different paths. This is the default.
+    #[default]
+    Strict,
+    /// Remove trailing slashes before routing, so `/status/` is treated as `/status`.
+    Ignore,
+    /// Response a `308 Permanent Redirect` to the path without trailing slashes.
+    Redirect,
+}
+
+/// How [`Router`] treats `.` and `..` parts in remain path, include percent-encoded ones like
+/// `%2E%2E`.
+///
+/// Note that the url parser already resolves them in request target when server receives a
+/// request, so this policy mostly affects paths rewritten by [`Context::next_with`].
+///
+/// A `..%2F..` part is not a `..` part, it's kept by all policies, and a [catch-all item] keeps
+/// the `%2F` in it's value, so the value does not contain `..` parts either.
+///
+/// [`Router`]: struct.Router.html
+/// [`Context::next_with`]: ../struct.Context.html#method.next_with
+/// [catch-all item]: struct.Router.html#catch-all-item
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum DotSegments {
+    /// Keep them as is, they are matched as normal parts. This is the default.
+    #[default]
+    Keep,
+    /// Response `400 Bad Request` for paths contain them.
+    Reject,
+    /// Resolve them like a file system path, `..` can't go outside of the remain path.
+    Resolve,
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct PathPolicy {
+    pub trailing_slash: TrailingSlash,
+    pub merge_slashes: bool,
+    pub dot_segments: DotSegments,
+}
+
+pub enum Normalized {
+    Keep,
+    Rewrite(String),
+    Redirect(String),
+    Reject,
+}
+
+impl PathPolicy {
+    /// Normalize `remain_path`, `at_root` means nothing before it in full request path.
+    ///
+    /// Nothing is allocated unless the path needs to be changed.
+    pub fn normalize(self, remain_path: &str, at_root: bool) -> Normalized {
+        let mut path = Cow::Borrowed(remain_path);
+
+        if self.merge_slashes && path.contains("//") {
+            let mut merged = String::with_capacity(path.len());
+            for c in path.chars() {
+                if !(c == '/' && merged.ends_with('/')) {
+                    merged.push(c);
+                }
+            }
+            path = Cow::Owned(merged);
+        }
+
+        if self.dot_segments != DotSegments::Keep
+            && path.split('/').skip(1).any(|part| dot_part(part).is_some())
+        {
+            if self.dot_segments == DotSegments::Reject {
+                return Normalized::Reject;
+            }
+            path = Cow::Owned(resolve_dots(&path));
+        }
+
+        let mut redirect = false;
+        if self.trailing_slash != TrailingSlash::Strict {
+            let trimmed = path.trim_end_matches('/');
+            // full path `/` can't be trimmed
+            let len = if trimmed.is_empty() && at_root { 1 } else { trimmed.len() };
+            if len != path.len() {
+                redirect = self.trailing_slash == TrailingSlash::Redirect;
+                match path {
+                    Cow::Borrowed(p) => path = Cow::Borrowed(&p[..len]),
+                    Cow::Owned(ref mut p) => p.truncate(len),
+                }
+            }
+        }
+
+        if path == remain_path {
+            Normalized::Keep
+        } else if redirect {
+            Normalized::Redirect(path.into_owned())
+        } else {
+            Normalized::Rewrite(path.into_owned())
+        }
+    }
+}
+
+/// If `part` is a `.` or `..`, maybe percent-encoded like `%2E%2E`, returns the decoded one.
+fn dot_part(part: &str) -> Option<&'static str> {
+    match &*percent::decode(part, true) {
+        "." => Some("."),
+        ".." => Some(".."),
+        _ => None,
+    }
+}
+
+fn resolve_dots(path: &str) -> String {
+    let mut parts: Vec<&str> = vec![];
+    let mut ends_with_dot = false;
+    for part in path.split('/').skip(1) {
+        let dot = dot_part(part);
+        ends_with_dot = dot.is_some();
+        match dot {
+            Some(".") => {}
+            Some(_) => {
+                parts.pop();
+            }
+            None => parts.push(part),
+        }
+    }
+
+    let mut resolved = String::with_capacity(path.len());
+    for part in parts {
+        resolved.push('/');
+        resolved.push_str(part);
+    }
+    if ends_with_dot {
+        resolved.push('/');
+    }
+    resolved
+}
@@ -0,0 +1,353 @@
+use {
+    crate::middleware::router::percent,
+    std::{borrow::Cow, cmp::Reverse, ops::Range},
+};
+
+/// Captured args, name and value.
+pub type Args<'s, 'p> = Vec<(&'s Cow<'static, str>, Cow<'p, str>)>;
+
+/// A path part pattern, like `user`, `{id}`, `{id:int}` or `{name}.{ext}`.
+pub struct Segment {
+    raw: String,
+    pieces: Vec<Piece>,
+}
+
+enum Piece {
+    Literal(String),
+    Param(Cow<'static, str>, Constraint),
+}
+
+enum Constraint {
+    Any,
+    Int,
+    Uint,
+    Alpha,
+    Alnum,
+    Hex,
+    #[cfg(feature = "regex")]
+    Regex(Box<regex::Regex>),
+}
+
+impl Constraint {
+    fn parse(raw: &str, s: &str) -> Self {
+        match s {
+            "int" => Self::Int,
+            "uint" => Self::Uint,
+            "alpha" => Self::Alpha,
+            "alnum" => Self::Alnum,
+            "hex" => Self::Hex,
+            #[cfg(feature = "regex")]
+            re => match regex::Regex::new(&format!("^(?:{re})$")) {
+                Ok(re) => Self::Regex(Box::new(re)),
+                Err(e) => invalid(raw, &format!("bad regex constraint: {e}")),
+            },
+            #[cfg(not(feature = "regex"))]
+            other => invalid(
+                raw,
+                &format!("unknown constraint `{other}`, enable `regex` feature to use regex"),
+            ),
+        }
+    }
+
+    fn check(&self, value: &str) -> bool {
+        let all = |f: fn(&u8) -> bool| value.as_bytes().iter().all(f);
+        match self {
+            Self::Any => true,
+            Self::Int => {
+                let digits = value.strip_prefix('-').unwrap_or(value);
+                !digits.is_empty() && digits.bytes().all(|b| b.is_ascii_digit())
+            }
+            Self::Uint => all(u8::is_ascii_digit),
+            Self::Alpha => all(u8::is_ascii_alphabetic),
+            Self::Alnum => all(u8::is_ascii_alphanumeric),
+            Self::Hex => all(u8::is_ascii_hexdigit),
+            #[cfg(feature = "regex")]
+            Self::Regex(re) => re.is_match(value),
+        }
+    }
+}
+
+fn invalid(raw: &str, reason: &str) -> ! {
+    panic!("invalid router path part `{}`: {}", raw, reason)
+}
+
+impl Segment {
+    /// Parse a path part, returns `None` if it's a normal part without any arg.
+    ///
+    /// ## Panics
+    ///
+    /// When the part is malformed.
+    pub fn parse(raw: &str) -> Option<Self> {
+        if !raw.contains(['{', '}']) {
+            return None;
+        }
+
+        let mut pieces = vec![];
+        let mut rest = raw;
+        while !rest.is_empty() {
+            if let Some(param) = rest.strip_prefix('{') {
+                if let Some(Piece::Param(..)) = pieces.last() {
+                    invalid(raw, "two args must be separated by some text");
+                }
+                let end = find_close_brace(param).unwrap_or_else(|| invalid(raw, "missing `}`"));
+                let (name, constraint) = match param[..end].split_once(':') {
+                    Some((name, constraint)) => (name, Constraint::parse(raw, constraint)),
+                    None => (&param[..end], Constraint::Any),
+                };
+                if name.is_empty() {
+                    invalid(raw, "arg name is empty");
+                }
+                if name.starts_with('*') {
+                    invalid(raw, "catch-all arg must be a whole part and the last part of path");
+                }
+                pieces.push(Piece::Param(Cow::Owned(name.to_owned()), constraint));
+                rest = &param[end + 1..];
+            } else {
+                let end = rest.find('{').unwrap_or(rest.len());
+                if rest[..end].contains('}') {
+                    invalid(raw, "unexpected `}`");
+                }
+                pieces.push(Piece::Literal(rest[..end].to_owned()));
+                rest = &rest[end..];
+            }
+        }
+
+        Some(Self { raw: raw.to_owned(), pieces })
+    }
+
+    /// The raw pattern text.
+    pub fn raw(&self) -> &str {
+        &self.raw
+    }
+
+    /// Sort key of sibling parts, smaller one is tried first.
+    ///
+    /// Parts contain text are tried first, longer text first. Then single arg with constraint,
+    /// and at last single arg without constraint.
+    pub fn priority(&self) -> (u8, Reverse<usize>) {
+        match self.pieces.as_slice() {
+            [Piece::Param(_, Constraint::Any)] => (2, Reverse(0)),
+            [Piece::Param(..)] => (1, Reverse(0)),
+            pieces => {
+                let text = pieces
+                    .iter()
+                    .map(|piece| if let Piece::Literal(l) = piece { l.len() } else { 0 })
+                    .sum();
+                (0, Reverse(text))
+            }
+        }
+    }
+
+    /// Match a decoded path part, push captured args into `args` if matches.
+    ///
+    /// Literal text is compared ignoring ASCII case when `ignore_case` is true.
+    // `part` is a `Cow` so captured values can borrow from request when it's not decoded
+    #[allow(clippy::ptr_arg)]
+    pub fn matches<'s, 'p>(
+        &'s self, part: &Cow<'p, str>, ignore_case: bool, args: &mut Args<'s, 'p>,
+    ) -> bool {
+        let mut captures = vec![];
+        if !match_pieces(&self.pieces, part, 0, ignore_case, &mut captures) {
+            return false;
+        }
+        args.extend(captures.into_iter().map(|(name, range)| {
+            let value = match part {
+                Cow::Borrowed(part) => Cow::Borrowed(&part[range]),
+                Cow::Owned(part) => Cow::Owned(part[range].to_owned()),
+            };
+            (name, value)
+        }));
+        true
+    }
+
+    /// Fill args into this pattern, returns the name of first missing arg if failed.
+    pub fn fill<'s, 'a, F>(&'s self, mut arg: F, output: &mut String) -> Result<(), &'s str>
+    where
+        F: FnMut(&str) -> Option<&'a str>,
+    {
+        for piece in &self.pieces {
+            match piece {
+                Piece::Literal(l) => output.push_str(l),
+                Piece::Param(name, _) => {
+                    percent::encode_to(arg(name).ok_or_else(|| name.as_ref())?, false, output);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn find_close_brace(s: &str) -> Option<usize> {
+    let mut depth = 0_usize;
+    for (i, c) in s.char_indices() {
+        match c {
+            '{' => depth += 1,
+            '}' if depth == 0 => return Some(i),
+            '}' => depth -= 1,
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Compare path text with literal `l`, ASCII case is ignored when `ignore_case` is true.
+fn text_eq(text: &str, l: &str, ignore_case: bool) -> bool {
+    if ignore_case {
+        text.eq_ignore_ascii_case(l)
+    } else {
+        text == l
+    }
+}
+
+/// Match `pieces` with `part[start..]`, captured args are recorded as ranges of `part`.
+fn match_pieces<'s>(
+    pieces: &'s [Piece], part: &str, start: usize, ignore_case: bool,
+    captures: &mut Vec<(&'s Cow<'static, str>, Range<usize>)>,
+) -> bool {
+    let rest_part = &part[start..];
+    match pieces {
+        [] => rest_part.is_empty(),
+        [Piece::Literal(l), rest @ ..] => {
+            rest_part.get(..l.len()).is_some_and(|text| text_eq(text, l, ignore_case))
+                && match_pieces(rest, part, start + l.len(), ignore_case, captures)
+        }
+        [Piece::Param(name, constraint)] => {
+            let matched = !rest_part.is_empty() && constraint.check(rest_part);
+            if matched {
+                captures.push((name, start..part.len()));
+            }
+            matched
+        }
+        [Piece::Param(name, constraint), rest @ ..] => {
+            let next_literal = match rest.first() {
+                Some(Piece::Literal(l)) => l.as_str(),
+                _ => unreachable!("args are always separated by text"),
+            };
+            // longest value first, so `{name}.{ext}` matches `a.tar.gz` as `a.tar` and `gz`
+            for pos in (1..=rest_part.len().saturating_sub(next_literal.len())).rev() {
+                let found = rest_part
+                    .get(pos..pos + next_literal.len())
+                    .is_some_and(|text| text_eq(text, next_literal, ignore_case));
+                if !found || !constraint.check(&rest_part[..pos]) {
+                    continue;
+                }
+                captures.push((name, start..start + pos));
+                if match_pieces(rest, part, start + pos, ignore_case, captures) {
+                    return true;
+                }
+                captures.pop();
+            }
+            false
+        }
+    }
+}
+
+/// Check a router table path at compile time, used by the [`routes!`] macro and route attribute
+/// macros like `#[get("/users/{id}")]`.
+///
+/// It follows the same rules as parsing path at runtime, except constraints are not checked.
+///
+/// ## Panics
+///
+/// When the path is malformed, it's a compile error when evaluated in const context.
+///
+/// [`routes!`]: ../macro.routes.html
+pub const fn check_path(path: &str) {
+    let bytes = path.as_bytes();
+    let mut depth = 0_usize;
+    let (mut part_start, mut arg_start, mut last_arg_end) = (0, 0, usize::MAX);
+    let mut i = 0;
+    while i < bytes.len() {
+        match (depth, bytes[i]) {
+            (0, b'{') => {
+                assert!(i != last_arg_end, "two args must be separated by some text");
+                depth = 1;
+                arg_start = i + 1;
+            }
+            (0, b'}') => panic!("{}", "unexpected `}` in router path"),
+            (0, b'/') => {
+                part_start = i + 1;
+                last_arg_end = usize::MAX;
+            }
+            (_, b'/') => panic!("router path arg can't contains `/`"),
+            (_, b'{') => depth += 1,
+            (1, b'}') => {
+                depth = 0;
+                last_arg_end = i + 1;
+                let catch_all = bytes[arg_start] == b'*';
+                let name_start = if catch_all { arg_start + 1 } else { arg_start };
+                assert!(
+                    i != name_start && bytes[name_start] != b':',
+                    "router path arg name is empty"
+                );
+                assert!(
+                    !catch_all || (arg_start == part_start + 1 && i + 1 == bytes.len()),
+                    "catch-all arg must be a whole part and the last part of path"
+                );
+            }
+            (_, b'}') => depth -= 1,
+            _ => {}
+        }
+        i += 1;
+    }
+    assert!(depth == 0, "{}", "missing `}` in router path");
+}
+
+/// Returns arg name if `part` is a catch-all arg like `{*path}`.
+pub fn catch_all_name(part: &str) -> Option<&str> {
+    part.strip_prefix("{*").and_then(|name| name.strip_suffix('}'))
+}
+
+/// A arg in route pattern, as `(name, constraint, is_catch_all)`.
+#[cfg(feature = "openapi")]
+pub type PatternArg<'p> = (&'p str, Option<&'p str>, bool);
+
+/// Split args out of a full route `pattern`, returns the pattern with only arg names in braces,
+/// like `/user/{id}/{path}` for `/user/{id:int}/{*path}`, and args in it.
+#[cfg(feature = "openapi")]
+pub fn split_args(pattern: &str) -> (String, Vec<PatternArg<'_>>) {
+    let mut plain = String::with_capacity(pattern.len());
+    let mut args = vec![];
+    let mut rest = pattern;
+    while let Some(start) = rest.find('{') {
+        plain.push_str(&rest[..=start]);
+        let param = &rest[start + 1..];
+        let end = find_close_brace(param).unwrap_or(param.len());
+        let (name, constraint) = match param[..end].split_once(':') {
+            Some((name, constraint)) => (name, Some(constraint)),
+            None => (&param[..end], None),
+        };
+        let catch_all = name.starts_with('*');
+        let name = name.trim_start_matches('*');
+        plain.push_str(name);
+        plain.push('}');
+        args.push((name, constraint, catch_all));
+        rest = param.get(end + 1..).unwrap_or_default();
+    }
+    plain.push_str(rest);
+    (plain, args)
+}
+
+/// Build url path from a full route `pattern`, `arg` gives value of args.
+///
+/// Returns the name of first missing arg if failed.
+pub fn fill_pattern<'a, F>(pattern: &str, mut arg: F) -> Result<String, String>
+where
+    F: FnMut(&str) -> Option<&'a str>,
+{
+    let mut url = String::with_capacity(pattern.len());
+    for part in pattern.split('/').skip(1) {
+        url.push('/');
+        if let Some(name) = catch_all_name(part) {
+            percent::encode_to(arg(name).ok_or_else(|| name.to_owned())?, true, &mut url);
+        } else if let Some(segment) = Segment::parse(part) {
+            segment.fill(&mut arg, &mut url).map_err(str::to_owned)?;
+        } else {
+            url.push_str(part);
+        }
+    }
+    if url.is_empty() {
+        url.push('/');
+    }
+    Ok(url)
+}
@@ -0,0 +1,40 @@
+use {
+    crate::{
+        context::Entry,
+        middleware::{Child, Visitor},
+        Context, Middleware, Result,
+    },
+    async_trait::async_trait,
+    std::sync::Arc,
+};
+
+/// A router table item with scoped middleware, created by `wrap` of router setter.
+///
+/// Layers run in onion order before the item's middleware, and the remaining middleware after
+/// this router are still reachable by `next` of the item's middleware.
+pub struct Scoped<Ex> {
+    /// Layers, then the inner middleware.
+    list: Vec<Entry<Ex>>,
+    inner: Arc<dyn Middleware<Ex>>,
+}
+
+impl<Ex> Scoped<Ex> {
+    pub fn new(mut layers: Vec<Entry<Ex>>, inner: Arc<dyn Middleware<Ex>>) -> Self {
+        layers.push(Entry::from(Arc::clone(&inner)));
+        Self { list: layers, inner }
+    }
+}
+
+#[async_trait]
+impl<Ex> Middleware<Ex> for Scoped<Ex>
+where
+    Ex: Send + Sync + 'static,
+{
+    async fn handle(&self, mut ctx: Context<'_, Ex>) -> Result {
+        ctx.next_through(&self.list).await
+    }
+
+    fn children(&self, visitor: &mut dyn Visitor) {
+        visitor.child(Child::new(self.inner.as_ref()));
+    }
+}
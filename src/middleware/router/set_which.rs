@@ -41,6 +41,7 @@ impl<Ex> SetWhich<Ex> for SetFallback {
 #[derive(Debug)]
 pub struct SetTableItem {
     pub path: Cow<'static, str>,
+    pub name: Option<Cow<'static, str>>,
 }
 
 impl<Ex> SetWhich<Ex> for SetTableItem {
@@ -49,6 +50,9 @@ impl<Ex> SetWhich<Ex> for SetTableItem {
         R: RouterLike<Ex>,
         M: Middleware<Ex> + 'static,
     {
+        if let Some(name) = self.name {
+            router.set_route_name(name, self.path.clone());
+        }
         router.insert_to_router_table(self.path, middleware);
         router
     }
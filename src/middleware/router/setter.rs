@@ -1,13 +1,15 @@
 use {
     crate::{
+        context::Entry,
         impl_all_http_method, impl_method, impl_router_like_pub_fn,
         middleware::router::{
+            scope::Scoped,
             set_which::{SetEndpoint, SetFallback, SetTableItem, SetWhich},
             MethodRouter, Router, RouterLike,
         },
         Method, Middleware,
     },
-    std::borrow::Cow,
+    std::{borrow::Cow, sync::Arc},
 };
 
 /// Router editing environment.
@@ -21,6 +23,7 @@ pub struct RouterSetter<R, Sw, Ex> {
     router: R,
     sub_router: Router<Ex>,
     method_router: MethodRouter<Ex>,
+    layers: Vec<Entry<Ex>>,
     setter: Sw,
 }
 
@@ -34,6 +37,7 @@ where
             router,
             method_router: MethodRouter::default(),
             sub_router: Router::default(),
+            layers: vec![],
             setter: SetEndpoint {},
         }
     }
@@ -49,6 +53,7 @@ where
             router,
             method_router: MethodRouter::default(),
             sub_router: Router::default(),
+            layers: vec![],
             setter: SetFallback {},
         }
     }
@@ -65,18 +70,51 @@ where
             router,
             method_router: MethodRouter::default(),
             sub_router: Router::default(),
-            setter: SetTableItem { path: path.into() },
+            layers: vec![],
+            setter: SetTableItem { path: path.into(), name: None },
         }
     }
 
     impl_router_like_pub_fn! { Ex }
 
+    /// Give this router table item a name, then you can use [`Context::url_for`] to build url of
+    /// it.
+    ///
+    /// [`Context::url_for`]: ../struct.Context.html#method.url_for
+    #[must_use]
+    pub fn name<N: Into<Cow<'static, str>>>(mut self, name: N) -> Self {
+        self.setter.name.replace(name.into());
+        self
+    }
+
+    /// Add a scoped middleware to this router table item, see *[Router - Scoped Middleware]*.
+    ///
+    /// [Router - Scoped Middleware]: struct.Router.html#scoped-middleware
+    #[must_use]
+    pub fn wrap<M: Middleware<Ex> + 'static>(mut self, middleware: M) -> Self {
+        self.layers.push(Entry::new(None, middleware));
+        self
+    }
+
     /// Finish this router table editing.
     pub fn done(self) -> R
     where
         Ex: Send + Sync + 'static,
     {
-        self.setter.set_to_target(self.router, self.sub_router)
+        let (router, sub_router) = (self.router, self.sub_router);
+        Self::finish(self.setter, router, self.layers, sub_router)
+    }
+
+    fn finish<M>(setter: SetTableItem, router: R, layers: Vec<Entry<Ex>>, middleware: M) -> R
+    where
+        M: Middleware<Ex> + 'static,
+        Ex: Send + Sync + 'static,
+    {
+        if layers.is_empty() {
+            setter.set_to_target(router, middleware)
+        } else {
+            setter.set_to_target(router, Scoped::new(layers, Arc::new(middleware)))
+        }
     }
 }
 
@@ -97,6 +135,10 @@ where
     ) {
         self.sub_router.insert_to_router_table(path, middleware);
     }
+
+    fn set_route_name(&mut self, name: Cow<'static, str>, path: Cow<'static, str>) {
+        self.sub_router.set_route_name(name, path);
+    }
 }
 
 #[allow(clippy::use_self)]
@@ -122,9 +164,29 @@ where
         self.router.at(path)
     }
 
+    /// Give this router table item a name, then you can use [`Context::url_for`] to build url of
+    /// it.
+    ///
+    /// [`Context::url_for`]: ../struct.Context.html#method.url_for
+    #[must_use]
+    pub fn name<N: Into<Cow<'static, str>>>(mut self, name: N) -> Self {
+        self.router.setter.name.replace(name.into());
+        self
+    }
+
+    /// Add a scoped middleware to this router table item, see *[Router - Scoped Middleware]*.
+    ///
+    /// [Router - Scoped Middleware]: struct.Router.html#scoped-middleware
+    #[must_use]
+    pub fn wrap<M: Middleware<Ex> + 'static>(mut self, middleware: M) -> Self {
+        self.router.layers.push(Entry::new(None, middleware));
+        self
+    }
+
     /// Finish setting uses `middleware`.
     pub fn is<M: Middleware<Ex> + 'static>(self, middleware: M) -> R {
-        self.router.setter.set_to_target(self.router.router, middleware)
+        let item = self.router;
+        RouterSetter::finish(item.setter, item.router, item.layers, middleware)
     }
 }
 
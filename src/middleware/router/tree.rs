@@ -0,0 +1,223 @@
+use {
+    crate::{
+        middleware::{
+            router::{
+                pattern::{catch_all_name, Args, Segment},
+                percent,
+            },
+            Child, RouteKind, Visitor,
+        },
+        Middleware,
+    },
+    std::{
+        borrow::Cow,
+        collections::{BTreeMap, HashMap},
+    },
+};
+
+/// Router table stored as a prefix tree of path segments.
+pub struct Node<Ex> {
+    handler: Option<Box<dyn Middleware<Ex>>>,
+    statics: BTreeMap<String, Self>,
+    /// Lowercase static segments to the smallest one of `statics` keys, only exists in case
+    /// insensitive mode.
+    folded: Option<HashMap<String, String>>,
+    params: Vec<(Segment, Self)>,
+    catch_all: Option<(Cow<'static, str>, Box<dyn Middleware<Ex>>)>,
+}
+
+/// A router table item matched by [`Node::find`].
+pub struct Found<'n, 'p, Ex> {
+    pub handler: &'n dyn Middleware<Ex>,
+    pub remain_path: &'p str,
+    pub args: Args<'n, 'p>,
+    pub route: String,
+}
+
+impl<Ex> Default for Node<Ex> {
+    fn default() -> Self {
+        Self {
+            handler: None,
+            statics: BTreeMap::new(),
+            folded: None,
+            params: vec![],
+            catch_all: None,
+        }
+    }
+}
+
+/// Split `path` to first segment and the rest, `path` must starts with `/`.
+fn split_segment(path: &str) -> (&str, &str) {
+    let path = &path[1..];
+    path.find('/').map_or((path, ""), |pos| (&path[..pos], &path[pos..]))
+}
+
+impl<Ex> Node<Ex> {
+    /// Insert `middleware` at `path`, replace the old one if exists.
+    ///
+    /// Returns whether a old one is replaced.
+    pub fn insert(&mut self, path: &str, middleware: Box<dyn Middleware<Ex>>) -> bool {
+        let mut node = self;
+        let mut segments = path.split('/').peekable();
+        while let Some(segment) = segments.next() {
+            if let Some(name) = catch_all_name(segment) {
+                assert!(
+                    segments.peek().is_none(),
+                    "invalid router path `{}`: catch-all arg must be the last part",
+                    path,
+                );
+                return node.catch_all.replace((Cow::Owned(name.to_owned()), middleware)).is_some();
+            }
+            node = if let Some(segment) = Segment::parse(segment) {
+                let pos = if let Some(pos) =
+                    node.params.iter().position(|(s, _)| s.raw() == segment.raw())
+                {
+                    pos
+                } else {
+                    let raw = segment.raw().to_owned();
+                    let child = node.child();
+                    node.params.push((segment, child));
+                    node.params.sort_by_key(|(s, _)| s.priority());
+                    node.params.iter().position(|(s, _)| s.raw() == raw).unwrap()
+                };
+                &mut node.params[pos].1
+            } else {
+                if !node.statics.contains_key(segment) {
+                    let child = node.child();
+                    node.statics.insert(segment.to_owned(), child);
+                    node.fold(segment);
+                }
+                node.statics.get_mut(segment).unwrap()
+            };
+        }
+        node.handler.replace(middleware).is_some()
+    }
+
+    /// A empty child node, in same case mode as `self`.
+    fn child(&self) -> Self {
+        Self { folded: self.folded.as_ref().map(|_| HashMap::new()), ..Self::default() }
+    }
+
+    /// Add static `segment` to the lowercase index, if it exists.
+    fn fold(&mut self, segment: &str) {
+        if let Some(ref mut folded) = self.folded {
+            let key =
+                folded.entry(segment.to_ascii_lowercase()).or_insert_with(|| segment.to_owned());
+            if segment < key.as_str() {
+                segment.clone_into(key);
+            }
+        }
+    }
+
+    /// Build or drop lowercase index of static segments in the whole tree, for case insensitive
+    /// mode.
+    pub fn fold_case(&mut self, enable: bool) {
+        self.folded = enable.then(|| {
+            let mut folded = HashMap::new();
+            // keys are sorted, so the smallest one is kept
+            for segment in self.statics.keys() {
+                folded.entry(segment.to_ascii_lowercase()).or_insert_with(|| segment.clone());
+            }
+            folded
+        });
+        for child in self.statics.values_mut().chain(self.params.iter_mut().map(|(_, c)| c)) {
+            child.fold_case(enable);
+        }
+    }
+
+    /// Find the deepest item which matches `path`.
+    ///
+    /// Children are tried by priority: static segment first, then args by [`Segment::priority`],
+    /// then catch-all arg. Only when a child subtree has no matched item, the next child is
+    /// tried. So for same router table the result is always same, and every node is visited at
+    /// most once.
+    ///
+    /// Every segment is percent-decoded before matching, after the path is split by `/`, so an
+    /// encoded `%2F` does not split a segment. Literal text is compared ignoring ASCII case
+    /// when `ignore_case` is true.
+    pub fn find<'n, 'p>(&'n self, path: &'p str, ignore_case: bool) -> Option<Found<'n, 'p, Ex>> {
+        let mut args = vec![];
+        let mut route = String::new();
+        let (handler, remain_path) = self.find_in(path, ignore_case, &mut args, &mut route)?;
+        Some(Found { handler, remain_path, args, route })
+    }
+
+    fn find_static(&self, segment: &str, ignore_case: bool) -> Option<(&String, &Self)> {
+        self.statics.get_key_value(segment).or_else(|| {
+            let key =
+                self.folded.as_ref().filter(|_| ignore_case)?.get(&segment.to_ascii_lowercase())?;
+            self.statics.get_key_value(key)
+        })
+    }
+
+    fn find_in<'n, 'p>(
+        &'n self, path: &'p str, ignore_case: bool, args: &mut Args<'n, 'p>, route: &mut String,
+    ) -> Option<(&'n dyn Middleware<Ex>, &'p str)> {
+        if !path.is_empty() {
+            let (segment, remain) = split_segment(path);
+            let segment = percent::decode(segment, false);
+            let route_len = route.len();
+
+            if let Some((key, child)) = self.find_static(&segment, ignore_case) {
+                route.push('/');
+                route.push_str(key);
+                if let Some(found) = child.find_in(remain, ignore_case, args, route) {
+                    return Some(found);
+                }
+                route.truncate(route_len);
+            }
+
+            let args_len = args.len();
+            for (pattern, child) in &self.params {
+                if pattern.matches(&segment, ignore_case, args) {
+                    route.push('/');
+                    route.push_str(pattern.raw());
+                    if let Some(found) = child.find_in(remain, ignore_case, args, route) {
+                        return Some(found);
+                    }
+                    args.truncate(args_len);
+                    route.truncate(route_len);
+                }
+            }
+
+            if let Some((ref name, ref handler)) = self.catch_all {
+                // keep `%2F`, so the value splits to same parts as the request path
+                args.push((name, percent::decode(&path[1..], true)));
+                route.push_str("/{*");
+                route.push_str(name);
+                route.push('}');
+                return Some((handler.as_ref(), ""));
+            }
+        }
+
+        self.handler.as_deref().map(|handler| (handler, path))
+    }
+
+    /// Visit all items as children of the router at `path`, see [`Middleware::children`].
+    ///
+    /// Normal parts are sorted by text, other parts are in match priority order. `names` are
+    /// route names by item path.
+    pub fn visit(
+        &self, path: &str, kind: RouteKind,
+        names: &HashMap<Cow<'static, str>, Vec<Cow<'static, str>>>, visitor: &mut dyn Visitor,
+    ) {
+        let child = |middleware, path: &str, kind| {
+            let names = names.get(path.get(1..).unwrap_or_default()).map_or(&[][..], Vec::as_slice);
+            Child::new(middleware).at(path.to_owned()).kind(kind).names(names)
+        };
+
+        if let Some(ref handler) = self.handler {
+            visitor.child(child(handler.as_ref(), path, kind));
+        }
+        for (segment, node) in &self.statics {
+            node.visit(&format!("{path}/{segment}"), RouteKind::Item, names, visitor);
+        }
+        for (pattern, node) in &self.params {
+            node.visit(&format!("{path}/{}", pattern.raw()), RouteKind::AnyItem, names, visitor);
+        }
+        if let Some((ref name, ref handler)) = self.catch_all {
+            let path = format!("{path}/{{*{name}}}");
+            visitor.child(child(handler.as_ref(), &path, RouteKind::CatchAll));
+        }
+    }
+}
@@ -0,0 +1,469 @@
+use {
+    crate::{context::Entry, middleware::Replaced, Context, Method, Middleware, Result},
+    async_trait::async_trait,
+    std::{
+        borrow::Cow,
+        fmt::{self, Display, Formatter},
+        ops::Deref,
+    },
+};
+
+/// What a route is in it's router, see [`RouteInfo`].
+///
+/// [`RouteInfo`]: struct.RouteInfo.html
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RouteKind {
+    /// The endpoint of the router you list routes from. Endpoints of sub routers use the kind of
+    /// the router table item they are mounted at.
+    Endpoint,
+    /// A router table item with a normal path, like `status`.
+    Item,
+    /// A router table item ends with a any part, like `{id}` or `{name}.{ext}`.
+    AnyItem,
+    /// A router table item ends with a catch-all part, like `{*path}`.
+    CatchAll,
+    /// The fallback of a router.
+    Fallback,
+}
+
+impl Display for RouteKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.pad(match self {
+            Self::Endpoint => "endpoint",
+            Self::Item => "item",
+            Self::AnyItem => "any-item",
+            Self::CatchAll => "catch-all",
+            Self::Fallback => "fallback",
+        })
+    }
+}
+
+/// A route in a router tree, returned by [`Router::routes`] and [`Amiya::routes`].
+///
+/// [`Router::routes`]: struct.Router.html#method.routes
+/// [`Amiya::routes`]: ../struct.Amiya.html#method.routes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RouteInfo {
+    /// Full path pattern of this route, like `/api/user/{id}`.
+    pub path: String,
+    /// The HTTP method this route accepts, `None` means any method.
+    pub method: Option<Method>,
+    /// What this route is in it's router.
+    pub kind: RouteKind,
+    /// Route name set by router setter's `name` method.
+    pub name: Option<String>,
+    /// Handler label set by [`Labeled`].
+    ///
+    /// [`Labeled`]: struct.Labeled.html
+    pub label: Option<String>,
+    /// Guard of this route set by [`GuardRouter`], like `host = example.com`, nested guards are
+    /// joined by `&`.
+    ///
+    /// [`GuardRouter`]: struct.GuardRouter.html
+    pub guard: Option<String>,
+    /// OpenAPI metadata set by [`Documented`], needs the `openapi` feature.
+    ///
+    /// [`Documented`]: struct.Documented.html
+    #[cfg(feature = "openapi")]
+    pub operation: Option<std::sync::Arc<crate::middleware::Operation>>,
+}
+
+impl RouteInfo {
+    fn new(path: &str, kind: RouteKind) -> Self {
+        Self {
+            path: path.to_owned(),
+            method: None,
+            kind,
+            name: None,
+            label: None,
+            guard: None,
+            #[cfg(feature = "openapi")]
+            operation: None,
+        }
+    }
+
+    /// Apply `mark` of a parent to this route, marks of inner middleware win, except guards are
+    /// joined by `&`, outer first.
+    fn mark(&mut self, mark: &Mark<'_>) {
+        match mark {
+            Mark::Method(method) => self.method = Some(*method),
+            Mark::Guard(guard) => {
+                let outer = self.guard.take();
+                self.guard = Some(
+                    outer.map_or_else(|| guard.to_string(), |outer| format!("{outer} & {guard}")),
+                );
+            }
+            Mark::Label(label) => self.label = Some((*label).to_owned()),
+            #[cfg(feature = "openapi")]
+            Mark::Operation(operation) => self.operation = Some(std::sync::Arc::clone(operation)),
+        }
+    }
+}
+
+/// A middleware contained in another middleware, given to a [`Visitor`] by
+/// [`Middleware::children`].
+///
+/// [`Visitor`]: trait.Visitor.html
+/// [`Middleware::children`]: trait.Middleware.html#method.children
+#[allow(missing_debug_implementations)]
+pub struct Child<'m> {
+    middleware: Box<dyn Walk + 'm>,
+    /// Path pattern it's mounted at relative to the parent, like `/users/{id}`, empty when it
+    /// handles the same path as the parent.
+    pub path: Cow<'m, str>,
+    /// What it is in the parent router, `None` if it's the same as the parent.
+    pub kind: Option<RouteKind>,
+    /// Whether the parent only passes it's routes through, like middleware of a [`Chain`] or
+    /// the one [`When`] wraps. A transparent child which contains no route is not a route by
+    /// itself, and when no child contains routes, the parent is a route instead.
+    ///
+    /// [`Chain`]: struct.Chain.html
+    /// [`When`]: struct.When.html
+    pub transparent: bool,
+    /// Route names of it, set by router setter's `name` method.
+    pub names: &'m [Cow<'static, str>],
+    /// What the parent adds to routes in it.
+    pub mark: Option<Mark<'m>>,
+}
+
+/// [`Middleware::children`] without the extra data type, so a visitor can walk into sub apps
+/// of other extra data types.
+trait Walk {
+    fn children(&self, visitor: &mut dyn Visitor);
+}
+
+impl<Ex> Walk for &dyn Middleware<Ex> {
+    fn children(&self, visitor: &mut dyn Visitor) {
+        (**self).children(visitor);
+    }
+}
+
+impl<'m> Child<'m> {
+    /// A child `middleware` which handles the same path as the parent.
+    pub fn new<Ex: 'm>(middleware: &'m dyn Middleware<Ex>) -> Self {
+        Self {
+            middleware: Box::new(middleware),
+            path: Cow::Borrowed(""),
+            kind: None,
+            transparent: false,
+            names: &[],
+            mark: None,
+        }
+    }
+
+    /// Give middleware contained in this child to `visitor`, see [`Middleware::children`].
+    ///
+    /// [`Middleware::children`]: trait.Middleware.html#method.children
+    pub fn children(&self, visitor: &mut dyn Visitor) {
+        self.middleware.children(visitor);
+    }
+
+    /// Set the relative path pattern it's mounted at.
+    #[must_use]
+    pub fn at<P: Into<Cow<'m, str>>>(mut self, path: P) -> Self {
+        self.path = path.into();
+        self
+    }
+
+    /// Set what it is in the parent router.
+    #[must_use]
+    pub const fn kind(mut self, kind: RouteKind) -> Self {
+        self.kind = Some(kind);
+        self
+    }
+
+    /// Mark it as transparent, see [`transparent`].
+    ///
+    /// [`transparent`]: #structfield.transparent
+    #[must_use]
+    pub const fn transparent(mut self) -> Self {
+        self.transparent = true;
+        self
+    }
+
+    /// Set route names of it.
+    #[must_use]
+    pub const fn names(mut self, names: &'m [Cow<'static, str>]) -> Self {
+        self.names = names;
+        self
+    }
+
+    /// Set what the parent adds to routes in it.
+    #[must_use]
+    pub fn mark(mut self, mark: Mark<'m>) -> Self {
+        self.mark = Some(mark);
+        self
+    }
+}
+
+/// What a parent middleware adds to routes of a [`Child`].
+///
+/// [`Child`]: struct.Child.html
+#[derive(Debug, Clone)]
+pub enum Mark<'m> {
+    /// The HTTP method routes accept, like what [`MethodRouter`] does.
+    ///
+    /// [`MethodRouter`]: struct.MethodRouter.html
+    Method(Method),
+    /// A guard of routes, like `host = example.com`.
+    Guard(Cow<'m, str>),
+    /// A handler label, like what [`Labeled`] does.
+    ///
+    /// [`Labeled`]: struct.Labeled.html
+    Label(&'m str),
+    /// OpenAPI metadata, like what [`Documented`] does, needs the `openapi` feature.
+    ///
+    /// [`Documented`]: struct.Documented.html
+    #[cfg(feature = "openapi")]
+    Operation(&'m std::sync::Arc<crate::middleware::Operation>),
+}
+
+/// Receives the structure of a middleware, see [`Middleware::children`].
+///
+/// [`Middleware::children`]: trait.Middleware.html#method.children
+pub trait Visitor {
+    /// Visit a middleware contained in current middleware.
+    fn child(&mut self, child: Child<'_>);
+
+    /// Current middleware has replaced a route when building, so it's a route conflict. The
+    /// default implementation ignores it.
+    fn replaced(&mut self, _replaced: &Replaced) {}
+}
+
+/// Collects routes of the children it visits, see [`Router::routes`].
+pub struct Collector<'r> {
+    /// The route of parent, children inherit it.
+    parent: &'r RouteInfo,
+    routes: &'r mut Vec<RouteInfo>,
+    described: bool,
+}
+
+impl<'r> Collector<'r> {
+    pub const fn new(parent: &'r RouteInfo, routes: &'r mut Vec<RouteInfo>) -> Self {
+        Self { parent, routes, described: false }
+    }
+
+    /// Collect routes of children visited by `visit`, at root path.
+    pub fn collect<F: FnOnce(&mut dyn Visitor)>(visit: F) -> Vec<RouteInfo> {
+        let root = RouteInfo::new("", RouteKind::Endpoint);
+        let mut routes = vec![];
+        visit(&mut Collector::new(&root, &mut routes));
+        routes
+    }
+
+    /// Push routes of `child` as `route`, returns whether it contains any route.
+    fn describe_routes(child: &Child<'_>, route: &RouteInfo, routes: &mut Vec<RouteInfo>) -> bool {
+        let mut collector = Collector::new(route, routes);
+        child.children(&mut collector);
+        collector.described
+    }
+
+    /// Push routes of `child`, or `route` itself if it contains no route.
+    fn describe(child: &Child<'_>, mut route: RouteInfo, routes: &mut Vec<RouteInfo>) {
+        if !Self::describe_routes(child, &route, routes) {
+            if route.path.is_empty() {
+                route.path.push('/');
+            }
+            routes.push(route);
+        }
+    }
+}
+
+impl Visitor for Collector<'_> {
+    fn child(&mut self, child: Child<'_>) {
+        let mut route = self.parent.clone();
+        route.path.push_str(&child.path);
+        if let Some(kind) = child.kind {
+            route.kind = kind;
+        }
+        // names are given to the item at the exact path, but not to it's fallback
+        if !child.path.is_empty() || route.kind == RouteKind::Fallback {
+            route.name = None;
+        }
+        if let Some(name) = child.names.first() {
+            route.name = Some(name.to_string());
+        }
+        if let Some(ref mark) = child.mark {
+            route.mark(mark);
+        }
+
+        if child.transparent {
+            self.described |= Self::describe_routes(&child, &route, self.routes);
+        } else {
+            Self::describe(&child, route, self.routes);
+            self.described = true;
+        }
+    }
+}
+
+/// Finds path pattern of the route named `name`, see [`Context::url_for`].
+pub struct NameFinder<'n> {
+    name: &'n str,
+    found: Option<String>,
+    /// Search in children if `true`, otherwise only check names of children.
+    deep: bool,
+}
+
+impl<'n> NameFinder<'n> {
+    /// Find route named `name` in a list of middleware, returns it's path pattern relative to
+    /// the list.
+    ///
+    /// In every middleware, it's own named children are preferred, then children are searched
+    /// by order, so the result is always the first one in order of [`Router::routes`].
+    pub fn find<Ex>(name: &'n str, list: &[Entry<Ex>]) -> Option<String> {
+        list.iter().find_map(|entry| Self::find_in(name, &Child::new(entry.middleware.as_ref())))
+    }
+
+    fn find_in(name: &'n str, child: &Child<'_>) -> Option<String> {
+        let mut finder = Self { name, found: None, deep: false };
+        child.children(&mut finder);
+        if finder.found.is_none() {
+            finder.deep = true;
+            child.children(&mut finder);
+        }
+        finder.found
+    }
+}
+
+impl Visitor for NameFinder<'_> {
+    fn child(&mut self, child: Child<'_>) {
+        if self.found.is_some() {
+            return;
+        }
+        self.found = if self.deep {
+            Self::find_in(self.name, &child).map(|path| format!("{}{path}", child.path))
+        } else {
+            child.names.iter().any(|n| n == self.name).then(|| child.path.into_owned())
+        };
+    }
+}
+
+/// Finds replaced routes of the children it visits, see [`Router::try_build`].
+pub struct ConflictFinder<'c> {
+    path: &'c str,
+    conflicts: &'c mut Vec<String>,
+}
+
+impl<'c> ConflictFinder<'c> {
+    pub const fn new(path: &'c str, conflicts: &'c mut Vec<String>) -> Self {
+        Self { path, conflicts }
+    }
+}
+
+impl Visitor for ConflictFinder<'_> {
+    fn child(&mut self, child: Child<'_>) {
+        let path = format!("{}{}", self.path, child.path);
+        child.children(&mut ConflictFinder::new(&path, self.conflicts));
+    }
+
+    fn replaced(&mut self, replaced: &Replaced) {
+        self.conflicts.push(replaced.message(self.path));
+    }
+}
+
+/// All routes of a router tree.
+///
+/// It derefs to a slice of [`RouteInfo`], and it's [`Display`] implementation gives a aligned
+/// table, one route per line, for printing in startup logs.
+///
+/// [`RouteInfo`]: struct.RouteInfo.html
+/// [`Display`]: https://doc.rust-lang.org/std/fmt/trait.Display.html
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Routes(pub(crate) Vec<RouteInfo>);
+
+impl Deref for Routes {
+    type Target = [RouteInfo];
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl IntoIterator for Routes {
+    type Item = RouteInfo;
+    type IntoIter = std::vec::IntoIter<RouteInfo>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+impl Display for Routes {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let method = |route: &RouteInfo| {
+            route.method.as_ref().map_or(Cow::Borrowed("*"), |m| Cow::Owned(m.to_string()))
+        };
+        let method_width = self.iter().map(|route| method(route).len()).max().unwrap_or(0);
+        let path_width = self.iter().map(|route| route.path.len()).max().unwrap_or(0);
+        for route in self.iter() {
+            let mut line = format!(
+                "{:<mw$}  {:<pw$}  {:<9}",
+                method(route),
+                route.path,
+                route.kind,
+                mw = method_width,
+                pw = path_width
+            );
+            if let Some(ref guard) = route.guard {
+                line.push_str("  [");
+                line.push_str(guard);
+                line.push(']');
+            }
+            if let Some(ref name) = route.name {
+                line.push_str("  @");
+                line.push_str(name);
+            }
+            if let Some(ref label) = route.label {
+                line.push_str("  ");
+                line.push_str(label);
+            }
+            writeln!(f, "{}", line.trim_end())?;
+        }
+        Ok(())
+    }
+}
+
+/// A middleware wrapper which gives `middleware` a label, the label will be shown in
+/// [`RouteInfo::label`] of routes it handles.
+///
+/// ## Examples
+///
+/// ```
+/// use amiya::{m, middleware::{Labeled, Router}};
+///
+/// let router = Router::new()
+///     .at("user/{id}")
+///     .get(Labeled::new("get user by id", m!(ctx => ctx.resp.set_body("user");)))
+///     .done();
+///
+/// print!("{}", router.routes());
+/// ```
+///
+/// [`RouteInfo::label`]: struct.RouteInfo.html#structfield.label
+#[allow(missing_debug_implementations)]
+pub struct Labeled<M> {
+    label: Cow<'static, str>,
+    middleware: M,
+}
+
+impl<M> Labeled<M> {
+    /// Create a labeled `middleware`.
+    pub fn new<L: Into<Cow<'static, str>>>(label: L, middleware: M) -> Self {
+        Self { label: label.into(), middleware }
+    }
+}
+
+#[async_trait]
+impl<M, Ex> Middleware<Ex> for Labeled<M>
+where
+    M: Middleware<Ex>,
+    Ex: Send + Sync + 'static,
+{
+    async fn handle(&self, ctx: Context<'_, Ex>) -> Result {
+        self.middleware.handle(ctx).await
+    }
+
+    fn children(&self, visitor: &mut dyn Visitor) {
+        visitor.child(Child::new(&self.middleware).mark(Mark::Label(&self.label)));
+    }
+}
@@ -0,0 +1,107 @@
+use {
+    crate::{
+        middleware::{Child, Collector, Routes, Visitor},
+        Context, Middleware, Result,
+    },
+    async_trait::async_trait,
+    std::{
+        fmt::{self, Debug, Formatter},
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc, PoisonError, RwLock,
+        },
+    },
+};
+
+/// Count of swaps of all slots, so things generated from routes, like the OpenAPI document, can
+/// know routes are changed.
+static SWAPS: AtomicUsize = AtomicUsize::new(0);
+
+/// Count of swaps of all slots since the program starts.
+#[cfg(feature = "openapi")]
+pub fn swap_count() -> usize {
+    SWAPS.load(Ordering::Acquire)
+}
+
+/// A middleware slot whose content can be replaced while the server is running, for routes
+/// changed by feature flags, plugins or admin configs without restart.
+///
+/// Clones of a `Swappable` share the same slot, so keep one clone, add another to the app, then
+/// [`swap`] in new middleware, like a new [`Router`], at any time.
+///
+/// Every request uses the middleware in the slot when it reaches the slot, a swap does not affect
+/// requests already in it, they finish with the old one, which is dropped after all of them
+/// finished.
+///
+/// ## Examples
+///
+/// ```
+/// use amiya::{m, middleware::{Router, Swappable}};
+///
+/// let routes = Swappable::new(Router::new().at("v1").is(m!(ctx => ctx.resp.set_body("v1");)));
+///
+/// let app = amiya::new().uses(routes.clone());
+///
+/// // later, maybe in another thread
+/// routes.swap(
+///     Router::new()
+///         .at("v1").is(m!(ctx => ctx.resp.set_body("v1");))
+///         .at("v2").is(m!(ctx => ctx.resp.set_body("v2");)),
+/// );
+/// ```
+///
+/// [`swap`]: #method.swap
+/// [`Router`]: struct.Router.html
+pub struct Swappable<Ex> {
+    slot: Arc<RwLock<Arc<dyn Middleware<Ex>>>>,
+}
+
+impl<Ex> Clone for Swappable<Ex> {
+    fn clone(&self) -> Self {
+        Self { slot: Arc::clone(&self.slot) }
+    }
+}
+
+impl<Ex> Debug for Swappable<Ex> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let current = self.current();
+        let routes = Collector::collect(|visitor| visitor.child(Child::new(current.as_ref())));
+        f.debug_struct("Swappable").field("routes", &Routes(routes)).finish()
+    }
+}
+
+impl<Ex> Swappable<Ex> {
+    /// Create a slot contains `middleware`.
+    #[must_use]
+    pub fn new<M: Middleware<Ex> + 'static>(middleware: M) -> Self {
+        Self { slot: Arc::new(RwLock::new(Arc::new(middleware))) }
+    }
+
+    /// Replace the middleware in slot by `middleware`, for all clones of this slot.
+    pub fn swap<M: Middleware<Ex> + 'static>(&self, middleware: M) {
+        let middleware: Arc<dyn Middleware<Ex>> = Arc::new(middleware);
+        *self.slot.write().unwrap_or_else(PoisonError::into_inner) = middleware;
+        SWAPS.fetch_add(1, Ordering::Release);
+    }
+
+    /// The middleware in slot now.
+    fn current(&self) -> Arc<dyn Middleware<Ex>> {
+        Arc::clone(&self.slot.read().unwrap_or_else(PoisonError::into_inner))
+    }
+}
+
+#[async_trait]
+impl<Ex> Middleware<Ex> for Swappable<Ex>
+where
+    Ex: Send + Sync + 'static,
+{
+    async fn handle(&self, ctx: Context<'_, Ex>) -> Result {
+        // do not hold the lock when handling, so swap is not blocked by running requests
+        let current = self.current();
+        current.handle(ctx).await
+    }
+
+    fn children(&self, visitor: &mut dyn Visitor) {
+        visitor.child(Child::new(self.current().as_ref()));
+    }
+}
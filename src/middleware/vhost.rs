@@ -0,0 +1,248 @@
+use {
+    crate::{
+        middleware::{request_host, Child, Mark, Replaced, Visitor},
+        Context, Middleware, Result, StatusCode,
+    },
+    async_trait::async_trait,
+    std::{
+        borrow::Cow,
+        collections::HashMap,
+        fmt::{self, Debug, Formatter},
+    },
+};
+
+enum Label {
+    Text(String),
+    Arg(String),
+    CatchAll(String),
+}
+
+/// A parsed host pattern like `{tenant}.example.com`.
+struct HostPattern {
+    labels: Vec<Label>,
+}
+
+impl HostPattern {
+    /// Parse `pattern`, returns `None` if it has no arg, so it's a exact host.
+    fn parse(pattern: &str) -> Option<Self> {
+        if !pattern.contains(['{', '}']) {
+            return None;
+        }
+        let labels = pattern
+            .split('.')
+            .enumerate()
+            .map(|(i, label)| {
+                let arg = label.strip_prefix('{').and_then(|label| label.strip_suffix('}'));
+                match arg {
+                    Some(name) if name.starts_with('*') && i == 0 && name.len() > 1 => {
+                        Label::CatchAll(name[1..].to_owned())
+                    }
+                    Some(name) if !name.is_empty() && !name.contains(['{', '}', '*']) => {
+                        Label::Arg(name.to_owned())
+                    }
+                    None if !label.is_empty() && !label.contains(['{', '}']) => {
+                        Label::Text(label.to_ascii_lowercase())
+                    }
+                    _ => panic!("invalid host pattern `{}`", pattern),
+                }
+            })
+            .collect();
+        Some(Self { labels })
+    }
+
+    /// Match lowercase `host`, returns captured args.
+    fn matches<'h>(&self, host: &'h str) -> Option<Vec<(&str, &'h str)>> {
+        let parts: Vec<_> = host.split('.').collect();
+        let (catch_all, labels) = match self.labels.split_first() {
+            Some((Label::CatchAll(name), rest)) => (Some(name), rest),
+            _ => (None, &self.labels[..]),
+        };
+        let count = parts.len().checked_sub(labels.len())?;
+        if catch_all.map_or(count != 0, |_| count == 0) {
+            return None;
+        }
+
+        let mut args = vec![];
+        if let Some(name) = catch_all {
+            // leading labels and the dots between them
+            let len = parts[..count].iter().map(|part| part.len() + 1).sum::<usize>() - 1;
+            args.push((name.as_str(), &host[..len]));
+        }
+        for (label, part) in labels.iter().zip(&parts[count..]) {
+            match label {
+                Label::Text(text) if text == part => {}
+                Label::Arg(name) if !part.is_empty() => args.push((name.as_str(), part)),
+                _ => return None,
+            }
+        }
+        Some(args)
+    }
+}
+
+struct Entry<Ex> {
+    host: String,
+    pattern: Option<HostPattern>,
+    middleware: Box<dyn Middleware<Ex>>,
+}
+
+/// The middleware for request diversion by `Host` header, to serve several domains, usually by
+/// one [`Amiya`] sub app for each, in one server.
+///
+/// There are three kinds of entries:
+///
+/// - Exact host, like `example.com`.
+/// - Wildcard host, some labels of the host are args, like `{tenant}.example.com`. A arg matches
+///   exactly one label, except a catch-all arg like `{*sub}.example.com`, it can only be the first
+///   label, and matches one or more labels, like `a.b` of `a.b.example.com`. Matched args can be
+///   got by [`Context::arg`], like path args of [`Router`].
+/// - The default entry, set by [`fallback`], used when no other entry matches.
+///
+/// Host is read from `Host` header, port and trailing dot are ignored, and matched ignoring ASCII
+/// case, args are in lowercase. Exact hosts are checked first, then wildcard hosts in the order
+/// they are added. If none matches and there is no default entry, the response is set to
+/// `421 Misdirected Request`. A request with more than one `Host` header is a `400 Bad Request`
+/// error.
+///
+/// Routes of entries are shown with a guard like `host = {tenant}.example.com` in
+/// [`Amiya::routes`]. A exact host set more than once is a conflict for [`Router::try_build`].
+///
+/// ## Examples
+///
+/// ```
+/// use amiya::{m, middleware::VirtualHosts};
+///
+/// let www = amiya::new().uses(m!(ctx => ctx.resp.set_body("home");));
+/// let tenant = amiya::new().uses(m!(ctx =>
+///     let body = format!("tenant {}", ctx.arg("tenant").unwrap());
+///     ctx.resp.set_body(body);
+/// ));
+///
+/// let app = amiya::new().uses(
+///     VirtualHosts::new()
+///         .host("www.example.com", www)
+///         .host("{tenant}.example.com", tenant)
+///         .fallback(m!(ctx => ctx.resp.set_body("unknown host");)),
+/// );
+/// ```
+///
+/// ## Panics
+///
+/// [`host`] panics if a arg is not a whole label, or has a empty name.
+///
+/// [`Amiya`]: ../struct.Amiya.html
+/// [`Amiya::routes`]: ../struct.Amiya.html#method.routes
+/// [`Context::arg`]: ../struct.Context.html#method.arg
+/// [`Router`]: struct.Router.html
+/// [`Router::try_build`]: struct.Router.html#method.try_build
+/// [`host`]: #method.host
+/// [`fallback`]: #method.fallback
+pub struct VirtualHosts<Ex> {
+    exact: HashMap<String, usize>,
+    entries: Vec<Entry<Ex>>,
+    fallback: Option<Box<dyn Middleware<Ex>>>,
+    replaced: Vec<Replaced>,
+}
+
+impl<Ex> Default for VirtualHosts<Ex> {
+    fn default() -> Self {
+        Self { exact: HashMap::new(), entries: vec![], fallback: None, replaced: vec![] }
+    }
+}
+
+impl<Ex> Debug for VirtualHosts<Ex> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("VirtualHosts")
+            .field("hosts", &self.entries.iter().map(|e| &e.host).collect::<Vec<_>>())
+            .field("fallback", &self.fallback.is_some())
+            .finish_non_exhaustive()
+    }
+}
+
+impl<Ex> VirtualHosts<Ex> {
+    /// Create a new `VirtualHosts`.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Use `middleware` for requests of `host`, a exact host or a wildcard host pattern.
+    ///
+    /// Setting a exact host more than once replaces the former one, and it's a conflict for
+    /// [`Router::try_build`].
+    ///
+    /// ## Panics
+    ///
+    /// When `host` is a invalid pattern, see *[Panics]*.
+    ///
+    /// [`Router::try_build`]: struct.Router.html#method.try_build
+    /// [Panics]: #panics
+    #[must_use]
+    pub fn host<H: AsRef<str>, M: Middleware<Ex> + 'static>(
+        mut self, host: H, middleware: M,
+    ) -> Self {
+        let host = host.as_ref().trim_end_matches('.');
+        let pattern = HostPattern::parse(host);
+        let host = if pattern.is_some() { host.to_owned() } else { host.to_ascii_lowercase() };
+        let entry = Entry { host: host.clone(), pattern, middleware: Box::new(middleware) };
+        if entry.pattern.is_none() {
+            if let Some(&index) = self.exact.get(&host) {
+                self.entries[index] = entry;
+                self.replaced.push(Replaced::Host(host.into()));
+                return self;
+            }
+            self.exact.insert(host, self.entries.len());
+        }
+        self.entries.push(entry);
+        self
+    }
+
+    /// Use `middleware` as the default entry, for requests whose host matches no other entry.
+    #[must_use]
+    pub fn fallback<M: Middleware<Ex> + 'static>(mut self, middleware: M) -> Self {
+        self.fallback = Some(Box::new(middleware));
+        self
+    }
+}
+
+#[async_trait]
+impl<Ex> Middleware<Ex> for VirtualHosts<Ex>
+where
+    Ex: Send + Sync + 'static,
+{
+    async fn handle(&self, ctx: Context<'_, Ex>) -> Result {
+        let host = request_host(ctx.req)?.unwrap_or_default();
+
+        if let Some(&index) = self.exact.get(&host) {
+            return self.entries[index].middleware.handle(ctx).await;
+        }
+
+        for entry in &self.entries {
+            if let Some(args) = entry.pattern.as_ref().and_then(|p| p.matches(&host)) {
+                for (name, value) in args {
+                    ctx.router_matches.insert(name.to_owned().into(), value.to_owned());
+                }
+                return entry.middleware.handle(ctx).await;
+            }
+        }
+
+        if let Some(ref fallback) = self.fallback {
+            return fallback.handle(ctx).await;
+        }
+
+        ctx.resp.set_status(StatusCode::MisdirectedRequest);
+        Ok(())
+    }
+
+    fn children(&self, visitor: &mut dyn Visitor) {
+        for replaced in &self.replaced {
+            visitor.replaced(replaced);
+        }
+        for entry in &self.entries {
+            let guard = Mark::Guard(Cow::Owned(format!("host = {}", entry.host)));
+            visitor.child(Child::new(entry.middleware.as_ref()).mark(guard));
+        }
+        if let Some(ref fallback) = self.fallback {
+            visitor.child(Child::new(fallback.as_ref()));
+        }
+    }
+}
@@ -0,0 +1,112 @@
+#![allow(dead_code)]
+
+use {
+    amiya::{Amiya, BuiltInExecutor},
+    std::{
+        io::{BufRead, BufReader, Read, Write},
+        net::{SocketAddr, TcpListener, TcpStream},
+        time::Duration,
+    },
+};
+
+/// A running test server, stopped when dropped.
+pub struct Server {
+    addr: SocketAddr,
+    stop: async_channel::Sender<()>,
+}
+
+/// A response read by [`Server::request`].
+#[derive(Debug)]
+pub struct Response {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+impl Response {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.iter().find(|(n, _)| n.eq_ignore_ascii_case(name)).map(|(_, v)| v.as_str())
+    }
+}
+
+/// Start `app` at a free local port.
+pub fn serve<Ex>(app: Amiya<BuiltInExecutor, Ex>) -> Server
+where
+    Ex: Default + Send + Sync + 'static,
+{
+    let addr = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+    let stop = app.listen(addr).unwrap();
+    Server { addr, stop }
+}
+
+impl Server {
+    /// Send a `GET` request of `target`.
+    pub fn get(&self, target: &str) -> Response {
+        self.request("GET", target, &[])
+    }
+
+    /// Send a request without body, and read the response.
+    pub fn request(&self, method: &str, target: &str, headers: &[(&str, &str)]) -> Response {
+        self.send(method, target, headers, "")
+    }
+
+    /// Send a request with `body`, and read the response.
+    pub fn send(
+        &self, method: &str, target: &str, headers: &[(&str, &str)], body: &str,
+    ) -> Response {
+        let mut stream = TcpStream::connect(self.addr).unwrap();
+        stream.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+
+        let mut req = format!("{method} {target} HTTP/1.1\r\nConnection: close\r\n");
+        if !headers.iter().any(|(name, _)| name.eq_ignore_ascii_case("host")) {
+            req.push_str(&format!("Host: {}\r\n", self.addr));
+        }
+        for (name, value) in headers {
+            req.push_str(&format!("{name}: {value}\r\n"));
+        }
+        if !body.is_empty() {
+            req.push_str(&format!("Content-Length: {}\r\n", body.len()));
+        }
+        req.push_str("\r\n");
+        req.push_str(body);
+        stream.write_all(req.as_bytes()).unwrap();
+
+        let mut reader = BufReader::new(stream);
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+        let status = line.split(' ').nth(1).unwrap().parse().unwrap();
+
+        let mut headers = vec![];
+        loop {
+            line.clear();
+            reader.read_line(&mut line).unwrap();
+            let header = line.trim_end();
+            if header.is_empty() {
+                break;
+            }
+            let (name, value) = header.split_once(':').unwrap();
+            headers.push((name.trim().to_owned(), value.trim().to_owned()));
+        }
+
+        let len = headers
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case("content-length"))
+            .map_or(0, |(_, value)| value.parse().unwrap());
+        let mut body = vec![0; len];
+        if method == "HEAD" {
+            // connection is closed after response, anything left is a wrongly sent body
+            body.clear();
+            reader.read_to_end(&mut body).unwrap();
+        } else {
+            reader.read_exact(&mut body).unwrap();
+        }
+
+        Response { status, headers, body: String::from_utf8(body).unwrap() }
+    }
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        let _ = self.stop.try_send(());
+    }
+}
@@ -0,0 +1,155 @@
+#![cfg(feature = "built-in-executor")]
+
+mod common;
+
+use amiya::{
+    m,
+    middleware::{Router, M},
+};
+
+fn rewrite(path: &'static str) -> M<()> {
+    m!(ctx => {
+        let mut req = ctx.req.clone();
+        req.url_mut().set_path(path);
+        ctx.next_with(&req).await
+    })
+}
+
+#[test]
+fn next_with_runs_inner_middleware_with_new_request() {
+    #[rustfmt::skip]
+    let router = Router::new()
+        .at("api").uses(rewrite("/api/new")).done()
+        .at("old").uses(rewrite("/new")).done();
+    let app = amiya::new()
+        .uses(m!(ctx => {
+            let method_override = ctx.req.header("X-HTTP-Method-Override");
+            if let Some(method) = method_override.and_then(|v| v.as_str().parse().ok()) {
+                let mut req = ctx.req.clone();
+                req.set_method(method);
+                ctx.next_with(&req).await?;
+            } else {
+                ctx.next().await?;
+            }
+            ctx.resp.insert_header("X-Method", ctx.req.method().to_string());
+            Ok(())
+        }))
+        .uses(router)
+        .uses(m!(ctx =>
+            let body = format!("{} {} {}", ctx.req.method(), ctx.req.url().path(), ctx.path());
+            ctx.resp.set_body(body);
+        ));
+    let server = common::serve(app);
+
+    // only the prefix matched by outer routers is stripped
+    assert_eq!(server.get("/api").body, "GET /api/new /new");
+    assert_eq!(server.get("/old").body, "GET /new /new");
+
+    let resp = server.request("POST", "/api", &[("X-HTTP-Method-Override", "PUT")]);
+    assert_eq!(resp.body, "PUT /api/new /new");
+    assert_eq!(resp.header("X-Method"), Some("POST"));
+}
+
+fn describe() -> M<()> {
+    m!(ctx =>
+        let body = format!("{} {:?} {:?}", ctx.matched_route(), ctx.arg("id"), ctx.arg("num"));
+        ctx.resp.set_body(body);
+    )
+}
+
+#[test]
+fn next_with_keeps_matched_prefix() {
+    #[rustfmt::skip]
+    let app = amiya::new()
+        .uses(Router::new().at("api").uses(rewrite("/api/new/7")).done())
+        .uses(Router::new().at("new/{num}").uses(describe()).done());
+    let server = common::serve(app);
+
+    assert_eq!(server.get("/api").body, "/api/new/{num} None Some(\"7\")");
+}
+
+#[test]
+fn next_with_clears_route_of_other_prefix() {
+    #[rustfmt::skip]
+    let app = amiya::new()
+        .uses(Router::new().at("old/{id}").uses(rewrite("/new/7")).done())
+        .uses(Router::new().at("new/{num}").uses(describe()).done());
+    let server = common::serve(app);
+
+    assert_eq!(server.get("/old/3").body, "/new/{num} None Some(\"7\")");
+}
+
+fn echo_twice() -> M<()> {
+    m!(ctx => {
+        let first = ctx.body_bytes(16).await?.to_vec();
+        let second = ctx.body_string(16).await?;
+        let body = format!("{} {}", String::from_utf8(first)?, second);
+        ctx.resp.set_body(body);
+        Ok(())
+    })
+}
+
+#[test]
+fn body_can_be_read_many_times() {
+    let app = amiya::new().uses(echo_twice());
+    let server = common::serve(app);
+
+    assert_eq!(server.send("POST", "/", &[], "hello").body, "hello hello");
+}
+
+#[test]
+fn body_json_after_body_bytes() {
+    let app = amiya::new()
+        .uses(m!(ctx => {
+            ctx.body_bytes(64).await?;
+            ctx.next().await
+        }))
+        .uses(m!(ctx => {
+            let data: Vec<u32> = ctx.body_json(64).await?;
+            ctx.resp.set_body(format!("{}", data.iter().sum::<u32>()));
+            Ok(())
+        }));
+    let server = common::serve(app);
+
+    assert_eq!(server.send("POST", "/", &[], "[1, 2, 3]").body, "6");
+    assert_eq!(server.send("POST", "/", &[], "[1, 2,").status, 422);
+}
+
+#[test]
+fn body_longer_than_limit_is_413() {
+    let app = amiya::new().uses(echo_twice());
+    let server = common::serve(app);
+
+    assert_eq!(server.send("POST", "/", &[], "a body longer than limit").status, 413);
+    assert_eq!(server.send("POST", "/", &[], "a short body").status, 200);
+}
+
+#[test]
+fn body_longer_than_limit_can_be_streamed() {
+    let app = amiya::new().uses(m!(ctx => {
+        let status = ctx.body_bytes(4).await.unwrap_err().status();
+        let body = ctx.body().unwrap().into_string().await?;
+        ctx.resp.set_body(format!("{} {}", status, body));
+        Ok(())
+    }));
+    let server = common::serve(app);
+
+    assert_eq!(server.send("POST", "/", &[], "hello").body, "413 hello");
+}
+
+#[test]
+fn body_stream_after_body_bytes() {
+    let app = amiya::new()
+        .uses(m!(ctx => {
+            ctx.body_bytes(16).await?;
+            ctx.next().await
+        }))
+        .uses(m!(ctx => {
+            let body = ctx.body().unwrap().into_string().await?;
+            ctx.resp.set_body(body);
+            Ok(())
+        }));
+    let server = common::serve(app);
+
+    assert_eq!(server.send("POST", "/", &[], "hello").body, "hello");
+}
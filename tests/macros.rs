@@ -0,0 +1,92 @@
+#![cfg(feature = "built-in-executor")]
+
+mod common;
+
+use amiya::{get, m, middleware::Router, post, prop_find, r#move, Context, Result};
+
+#[get("/users/{id:uint}")]
+async fn get_user(ctx: Context<'_, ()>, id: u8) -> Result {
+    ctx.resp.set_body(format!("get {}", id));
+    Ok(())
+}
+
+#[post("/users/{id:uint}")]
+async fn update_user(ctx: Context<'_, ()>, id: u8) -> Result {
+    ctx.resp.set_body(format!("update {}", id));
+    Ok(())
+}
+
+#[get("/files/{*path}")]
+async fn get_file(ctx: Context<'_, ()>, path: String) -> Result {
+    ctx.resp.set_body(path);
+    Ok(())
+}
+
+#[post("/files/{*path}")]
+async fn post_file(ctx: Context<'_, ()>, path: String) -> Result {
+    ctx.resp.set_body(path);
+    Ok(())
+}
+
+#[prop_find("/files/{*path}")]
+async fn find_file(ctx: Context<'_, ()>, path: String) -> Result {
+    ctx.resp.set_body(path);
+    Ok(())
+}
+
+#[r#move("/files/{*path}")]
+async fn move_file(ctx: Context<'_, ()>, path: String) -> Result {
+    ctx.resp.set_body(path);
+    Ok(())
+}
+
+#[test]
+fn serve_handlers() {
+    let router = Router::new()
+        .handler(get_user)
+        .handler(update_user)
+        .fallback()
+        .uses(m!(ctx => ctx.resp.set_body("fallback");));
+    assert_eq!(router.routes().len(), 3);
+    let server = common::serve(amiya::new().uses(router.build()));
+
+    assert_eq!(server.get("/users/7").body, "get 7");
+    assert_eq!(server.send("POST", "/users/7", &[], "").body, "update 7");
+    // matches `uint` but out of range of `u8`
+    assert_eq!(server.get("/users/300").status, 400);
+    assert_eq!(server.get("/users/x").body, "fallback");
+}
+
+#[test]
+fn handlers_at_same_path_are_merged() {
+    let router = Router::new()
+        .handler(get_file)
+        .handler(post_file)
+        .handler(find_file)
+        .handler(move_file)
+        .try_build()
+        .unwrap();
+    let methods: Vec<_> = router.routes().iter().filter_map(|route| route.method).collect();
+    assert_eq!(methods.len(), 4);
+}
+
+#[test]
+fn handler_replaced_by_setter_is_conflict() {
+    let router = Router::new()
+        .handler(get_file)
+        .at("files/{*path}")
+        .is(m!(ctx => ctx.resp.set_body("is");))
+        .handler(post_file);
+    // `get_file` is replaced, so it's not merged into the new method router
+    let methods: Vec<_> = router.routes().iter().filter_map(|route| route.method).collect();
+    assert_eq!(methods, [amiya::Method::Post]);
+
+    let conflicts = router.try_build().unwrap_err().conflicts().len();
+    assert_eq!(conflicts, 2);
+}
+
+#[test]
+fn compile_fail() {
+    let cases = trybuild::TestCases::new();
+    cases.compile_fail("tests/ui/*.rs");
+}
@@ -0,0 +1,78 @@
+#![cfg(feature = "built-in-executor")]
+
+mod common;
+
+use amiya::{
+    m,
+    middleware::{MethodRouter, Router},
+    Amiya, BuiltInExecutor, Method,
+};
+
+fn app() -> Amiya<BuiltInExecutor> {
+    #[rustfmt::skip]
+    let router = Router::new()
+        .at("page")
+            .get(m!(ctx => ctx.resp.set_body("page content");))
+        .done()
+        .at("form")
+            .post(m!(ctx => ctx.resp.set_body("posted");))
+        .done();
+    amiya::new().uses(router)
+}
+
+#[test]
+fn head_uses_get_handler_without_body() {
+    let server = common::serve(app());
+
+    let resp = server.request("HEAD", "/page", &[]);
+    assert_eq!(resp.status, 200);
+    assert_eq!(resp.header("content-length"), Some("12"));
+    assert_eq!(resp.body, "");
+}
+
+#[test]
+fn options_lists_allowed_methods() {
+    let server = common::serve(app());
+
+    let resp = server.request("OPTIONS", "/page", &[]);
+    assert_eq!(resp.status, 204);
+    assert_eq!(resp.header("allow"), Some("GET, HEAD, OPTIONS"));
+    assert_eq!(resp.body, "");
+
+    let resp = server.request("OPTIONS", "/form", &[]);
+    assert_eq!(resp.status, 204);
+    assert_eq!(resp.header("allow"), Some("POST, OPTIONS"));
+}
+
+#[test]
+fn not_allowed_method_is_405_with_allow() {
+    let server = common::serve(app());
+
+    let resp = server.request("DELETE", "/page", &[]);
+    assert_eq!(resp.status, 405);
+    assert_eq!(resp.header("allow"), Some("GET, HEAD, OPTIONS"));
+
+    let resp = server.get("/form");
+    assert_eq!(resp.status, 405);
+    assert_eq!(resp.header("allow"), Some("POST, OPTIONS"));
+
+    let resp = server.request("HEAD", "/form", &[]);
+    assert_eq!(resp.status, 405);
+    assert_eq!(resp.header("allow"), Some("POST, OPTIONS"));
+}
+
+#[test]
+fn webdav_methods() {
+    let dav = MethodRouter::new()
+        .method(Method::PropFind, m!(ctx => ctx.resp.set_body("propfind");))
+        .copy(m!(ctx => ctx.resp.set_body("copy");))
+        .lock(m!(ctx => ctx.resp.set_body("lock");));
+    let server = common::serve(amiya::new().uses(Router::new().at("dav").is(dav)));
+
+    assert_eq!(server.request("PROPFIND", "/dav", &[]).body, "propfind");
+    assert_eq!(server.request("COPY", "/dav", &[]).body, "copy");
+
+    let resp = server.request("MKCOL", "/dav", &[]);
+    assert_eq!(resp.status, 405);
+    assert_eq!(resp.header("allow"), Some("OPTIONS, COPY, LOCK, PROPFIND"));
+}
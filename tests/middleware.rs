@@ -0,0 +1,342 @@
+#![cfg(feature = "built-in-executor")]
+
+mod common;
+
+use amiya::{
+    async_trait, m,
+    middleware::{catch, chain, from_fn, when, Child, Mount, Router, Swappable, Visitor},
+    BuiltInExecutor, Context, Error, Middleware, Result, StatusCode,
+};
+
+#[test]
+fn chain_runs_in_onion_order() {
+    let app = amiya::with_ex::<Vec<&str>>()
+        .uses(m!(ctx: Vec<&str> => {
+            ctx.next().await?;
+            let body = ctx.ex.join(",");
+            ctx.resp.set_body(body);
+            Ok(())
+        }))
+        .uses(chain(vec![
+            m!(ctx: Vec<&str> => { ctx.ex.push("a"); ctx.next().await?; ctx.ex.push("a out"); Ok(()) }),
+            m!(ctx: Vec<&str> => { ctx.ex.push("b"); ctx.next().await?; ctx.ex.push("b out"); Ok(()) }),
+        ]))
+        .uses(m!(ctx: Vec<&str> => ctx.ex.push("c");));
+    let server = common::serve(app);
+
+    assert_eq!(server.get("/").body, "a,b,c,b out,a out");
+}
+
+#[test]
+fn when_runs_middleware_only_if_predicate_is_true() {
+    let app = amiya::new()
+        .uses(when(
+            |ctx| ctx.path().starts_with("/api"),
+            m!(ctx => {
+                ctx.next().await?;
+                ctx.resp.insert_header("X-Api", "1");
+                Ok(())
+            }),
+        ))
+        .uses(m!(ctx => ctx.resp.set_body(ctx.path().to_owned());));
+    let server = common::serve(app);
+
+    let resp = server.get("/api/users");
+    assert_eq!(resp.header("X-Api"), Some("1"));
+    assert_eq!(resp.body, "/api/users");
+
+    let resp = server.get("/users");
+    assert_eq!(resp.header("X-Api"), None);
+    assert_eq!(resp.body, "/users");
+}
+
+async fn show_error(err: Error, mut ctx: Context<'_, u32>) -> Result {
+    // inner middleware already run, so this does not call them again
+    ctx.next().await?;
+    let req_body = ctx.body_string(64).await?.to_owned();
+    let body = format!("{} after {} call(s): {}", err.status(), ctx.ex, req_body);
+    ctx.resp.set_status(StatusCode::ImATeapot);
+    ctx.resp.set_body(body);
+    Ok(())
+}
+
+async fn fail_again(err: Error, _: Context<'_, u32>) -> Result {
+    Err(Error::from_str(StatusCode::ServiceUnavailable, format!("handler failed on {}", err)))
+}
+
+fn failing() -> amiya::middleware::M<u32> {
+    m!(ctx: u32 => {
+        *ctx.ex += 1;
+        match ctx.path() {
+            "/ok" => {
+                ctx.resp.set_body("ok");
+                Ok(())
+            }
+            _ => Err(Error::from_str(StatusCode::Forbidden, "go away")),
+        }
+    })
+}
+
+#[test]
+fn catch_gives_error_to_async_handler() {
+    let app = amiya::with_ex::<u32>().uses(catch(m!(ctx: u32 => ctx.next().await), show_error));
+    let server = common::serve(app.uses(failing()));
+
+    let resp = server.send("POST", "/ok", &[], "body");
+    assert_eq!(resp.status, 200);
+    assert_eq!(resp.body, "ok");
+
+    let resp = server.send("POST", "/fail", &[], "body");
+    assert_eq!(resp.status, 418);
+    assert_eq!(resp.body, "403 after 1 call(s): body");
+
+    let app = amiya::with_ex::<u32>().uses(catch(m!(ctx: u32 => ctx.next().await), fail_again));
+    let server = common::serve(app.uses(failing()));
+    assert_eq!(server.get("/fail").status, 503);
+}
+
+#[test]
+fn scoped_middleware_run_before_item() {
+    #[rustfmt::skip]
+    let router = Router::new()
+        .at("admin")
+            .wrap(m!(ctx: Vec<&str> => { ctx.ex.push("auth"); ctx.next().await }))
+            .wrap(m!(ctx: Vec<&str> => { ctx.ex.push("log"); ctx.next().await }))
+            .at("users").get(m!(ctx: Vec<&str> => { ctx.ex.push("users"); ctx.next().await })).done()
+        .done();
+    let app = amiya::with_ex::<Vec<&str>>()
+        .uses(m!(ctx: Vec<&str> => {
+            ctx.next().await?;
+            let body = ctx.ex.join(",");
+            ctx.resp.set_body(body);
+            Ok(())
+        }))
+        .uses(router)
+        .uses(m!(ctx: Vec<&str> => ctx.ex.push("after");));
+    let server = common::serve(app);
+
+    assert_eq!(server.get("/admin/users").body, "auth,log,users,after");
+    assert_eq!(server.get("/admin/posts").body, "auth,log");
+    assert_eq!(server.get("/posts").body, "");
+}
+
+#[derive(Default)]
+struct Blog {
+    author: Option<String>,
+    views: u64,
+}
+
+#[test]
+fn mount_sub_app_with_own_extra_data() {
+    let blog = amiya::with_ex().uses(m!(ctx: Blog =>
+        ctx.ex.views += 1;
+        let body = format!("{:?} {} {:?}", ctx.ex.author, ctx.path(), ctx.arg("user"));
+        ctx.resp.set_body(body);
+    ));
+    let blog = Mount::with(blog, |ctx: &Context<'_, Vec<String>>| Blog {
+        author: ctx.ex.first().cloned(),
+        ..Blog::default()
+    })
+    .map_back(|blog, names| names.push(format!("views {}", blog.views)));
+
+    let app = amiya::with_ex::<Vec<String>>()
+        .uses(m!(ctx: Vec<String> => {
+            ctx.ex.push("amiya".to_owned());
+            ctx.next().await?;
+            let extra = ctx.ex.join(",");
+            ctx.resp.insert_header("X-Extra", extra);
+            Ok(())
+        }))
+        .uses(Router::new().at("{user}/blog").is(blog))
+        .uses(m!(ctx: Vec<String> => ctx.ex.push("after".to_owned());));
+    let server = common::serve(app);
+
+    let resp = server.get("/me/blog/post");
+    assert_eq!(resp.body, "Some(\"amiya\") /post Some(\"me\")");
+    assert_eq!(resp.header("X-Extra"), Some("amiya,views 1,after"));
+}
+
+#[test]
+fn url_for_in_mount_has_mount_prefix() {
+    #[rustfmt::skip]
+    let blog = amiya::new().uses(Router::new()
+        .at("target").name("target").get(m!(ctx => ctx.resp.set_body("target");)).done()
+        .at("link").get(m!(ctx =>
+            let url = ctx.url_for::<&str, &str>("target", &[])?;
+            ctx.resp.set_body(url);
+        )).done()
+    );
+    let app = amiya::new().uses(Router::new().at("blog").is(Mount::new(blog)));
+    let server = common::serve(app);
+
+    assert_eq!(server.get("/blog/link").body, "/blog/target");
+}
+
+#[test]
+fn swap_routes_at_runtime() {
+    let routes = Swappable::new(Router::new().at("v1").is(m!(ctx => ctx.resp.set_body("v1");)));
+    let app = amiya::new().uses(routes.clone());
+    let paths = |app: &amiya::Amiya<BuiltInExecutor>| {
+        app.routes().iter().map(|route| route.path.clone()).collect::<Vec<_>>()
+    };
+    assert_eq!(paths(&app), ["/v1"]);
+    let server = common::serve(app);
+
+    assert_eq!(server.get("/v1").body, "v1");
+    assert_eq!(server.get("/v2").status, 404);
+
+    #[rustfmt::skip]
+    routes.swap(Router::new()
+        .at("v1").is(m!(ctx => ctx.resp.set_body("v1 again");))
+        .at("v2").is(m!(ctx => ctx.resp.set_body("v2");))
+    );
+    assert_eq!(server.get("/v1").body, "v1 again");
+    assert_eq!(server.get("/v2").body, "v2");
+}
+
+fn push(name: &'static str) -> amiya::middleware::M<Vec<&'static str>> {
+    m!(ctx: Vec<&str> => {
+        ctx.ex.push(name);
+        ctx.next().await
+    })
+}
+
+#[test]
+fn named_middleware() {
+    let app = amiya::with_ex::<Vec<&str>>()
+        .uses(m!(ctx: Vec<&str> => {
+            ctx.next().await?;
+            let body = ctx.ex.join(",");
+            ctx.resp.set_body(body);
+            Ok(())
+        }))
+        .uses_as("log", push("log"))
+        .uses_as("auth", push("auth"))
+        .uses_as("router", push("router"))
+        .uses_before("auth", push("before auth"))
+        .uses_after("auth", push("after auth"))
+        .replace("log", push("new log"))
+        .remove("auth");
+    assert_eq!(app.middleware_names(), [None, Some("log"), None, None, Some("router")]);
+
+    let server = common::serve(app);
+    assert_eq!(server.get("/").body, "new log,before auth,after auth,router");
+}
+
+#[test]
+#[should_panic(expected = "log")]
+fn duplicate_middleware_name() {
+    let _ = amiya::new().uses_as("log", m!(ctx => ctx.next().await)).uses_as("log", Router::new());
+}
+
+#[test]
+#[should_panic(expected = "auth")]
+fn remove_unknown_middleware() {
+    let _ = amiya::new().uses_as("log", m!(ctx => ctx.next().await)).remove("auth");
+}
+
+#[test]
+fn edit_middleware_by_name() {
+    let app = amiya::new()
+        .uses_as("log", m!(ctx => ctx.next().await))
+        .uses_as("router", Router::new())
+        .uses_before_as("router", "auth", m!(ctx => ctx.next().await));
+    assert_eq!(app.middleware_names(), [Some("log"), Some("auth"), Some("router")]);
+
+    let app = app.try_remove("missing").unwrap_err();
+    let app = app.try_uses_as("log", m!(ctx => ctx.next().await)).unwrap_err();
+    let app = app.try_uses_after_as("auth", "log", m!(ctx => ctx.next().await)).unwrap_err();
+    let app = app.try_uses_after("auth", m!(ctx => ctx.next().await)).unwrap();
+    assert_eq!(app.middleware_names(), [Some("log"), Some("auth"), None, Some("router")]);
+}
+
+async fn tag(mut ctx: Context<'_, ()>) -> Result {
+    ctx.next().await?;
+    ctx.resp.insert_header("X-Tag", "1");
+    Ok(())
+}
+
+async fn hello(ctx: Context<'_, ()>) -> Result {
+    let body = format!("hello {}", ctx.arg("name").unwrap());
+    ctx.resp.set_body(body);
+    Ok(())
+}
+
+#[test]
+fn async_fn_as_middleware() {
+    let app = amiya::new().uses(tag).uses(Router::new().at("hello/{name}").get(hello).done());
+    let server = common::serve(app);
+
+    let resp = server.get("/hello/amiya");
+    assert_eq!(resp.body, "hello amiya");
+    assert_eq!(resp.header("X-Tag"), Some("1"));
+}
+
+#[test]
+fn closure_with_boxed_future_as_middleware() {
+    let greeting = String::from("hello");
+    let app = amiya::new().uses(from_fn(move |mut ctx| {
+        let greeting = greeting.clone();
+        Box::pin(async move {
+            ctx.next().await?;
+            let body = format!("{} {}", greeting, ctx.path());
+            ctx.resp.set_body(body);
+            Ok(())
+        })
+    }));
+    let server = common::serve(app);
+
+    assert_eq!(server.get("/amiya").body, "hello /amiya");
+}
+
+/// Uses `beta` for requests with `X-Beta` header, `stable` for others.
+struct Beta<A, B> {
+    beta: A,
+    stable: B,
+}
+
+#[async_trait]
+impl<A: Middleware<()>, B: Middleware<()>> Middleware<()> for Beta<A, B> {
+    async fn handle(&self, ctx: Context<'_, ()>) -> Result {
+        if ctx.req.header("X-Beta").is_some() {
+            self.beta.handle(ctx).await
+        } else {
+            self.stable.handle(ctx).await
+        }
+    }
+
+    fn children(&self, visitor: &mut dyn Visitor) {
+        visitor.child(Child::new(&self.beta));
+        visitor.child(Child::new(&self.stable));
+    }
+}
+
+fn beta_router() -> Router<()> {
+    #[rustfmt::skip]
+    let beta = Beta {
+        beta: Router::new()
+            .at("b").get(m!(ctx => ctx.resp.set_body("b");)).done()
+            .at("b").get(m!(ctx => ctx.resp.set_body("b2");)).done(),
+        stable: Router::new().at("a").name("a").get(m!(ctx => ctx.resp.set_body("a");)).done(),
+    };
+    #[rustfmt::skip]
+    let router = Router::new()
+        .at("x").is(beta)
+        .at("link").get(m!(ctx =>
+            let url = ctx.url_for::<&str, &str>("a", &[])?;
+            ctx.resp.set_body(url);
+        )).done();
+    router
+}
+
+#[test]
+fn custom_container_is_walked() {
+    let routes: Vec<_> = beta_router().routes().iter().map(|r| r.path.clone()).collect();
+    assert_eq!(routes, ["/link", "/x/b", "/x/a"]);
+
+    let conflicts = beta_router().try_build().unwrap_err();
+    assert_eq!(conflicts.conflicts(), ["`/x/b` is set more than once"]);
+
+    let server = common::serve(amiya::new().uses(beta_router()));
+    assert_eq!(server.get("/link").body, "/x/a");
+}
@@ -0,0 +1,125 @@
+#![cfg(all(feature = "built-in-executor", feature = "openapi"))]
+
+mod common;
+
+use {
+    amiya::{
+        m,
+        middleware::{
+            Documented, Labeled, MethodRouter, Mount, OpenApi, Operation, Param, Router, Swappable,
+        },
+        Method,
+    },
+    serde_json::{json, Value},
+};
+
+#[test]
+fn serve_document() {
+    let get_pet = Operation::new()
+        .summary("Get a pet")
+        .tag("pet")
+        .param(Param::query("fields").description("fields to return"))
+        .response_json(200, "The pet", json!({ "type": "object" }));
+    #[rustfmt::skip]
+    let app = amiya::new().uses(Router::new()
+        .at("openapi.json").is(OpenApi::new("Pet Store", "1.0.0").description("Pets"))
+        .at("pets")
+            .get(Labeled::new("List pets", m!(ctx => ctx.resp.set_body("[]");)))
+            .at("{id:uint}").uses(MethodRouter::new()
+                .get(Documented::new(get_pet, m!(ctx => ctx.resp.set_body("{}");)))
+                .method(Method::PropFind, m!(ctx => ctx.resp.set_body("");)))
+            .done()
+        .done()
+        .at("health").is(m!(ctx => ctx.resp.set_body("OK");))
+        .fallback().uses(m!(ctx => ctx.resp.set_body("fallback");))
+    );
+    let server = common::serve(app);
+
+    let resp = server.get("/openapi.json");
+    assert_eq!(resp.header("Content-Type"), Some("application/json"));
+    let doc: Value = serde_json::from_str(&resp.body).unwrap();
+    assert_eq!(doc["openapi"], "3.0.3");
+    assert_eq!(
+        doc["info"],
+        json!({ "title": "Pet Store", "version": "1.0.0", "description": "Pets" })
+    );
+
+    let paths = doc["paths"].as_object().unwrap();
+    let mut keys: Vec<_> = paths.keys().collect();
+    keys.sort();
+    assert_eq!(keys, ["/pets", "/pets/{id}"]);
+
+    let list = &doc["paths"]["/pets"]["get"];
+    assert_eq!(list["summary"], "List pets");
+    assert_eq!(list["responses"]["default"]["description"], "Default response");
+
+    let pet = doc["paths"]["/pets/{id}"].as_object().unwrap();
+    assert_eq!(pet.keys().collect::<Vec<_>>(), ["get"]);
+    let get = &pet["get"];
+    assert_eq!(get["summary"], "Get a pet");
+    assert_eq!(get["tags"], json!(["pet"]));
+    assert_eq!(get["parameters"][0]["name"], "id");
+    assert_eq!(get["parameters"][0]["in"], "path");
+    assert_eq!(get["parameters"][0]["schema"], json!({ "type": "integer", "minimum": 0 }));
+    assert_eq!(get["parameters"][1]["name"], "fields");
+    assert_eq!(get["parameters"][1]["in"], "query");
+    assert!(get["responses"]["200"]["content"]["application/json"].is_object());
+}
+
+#[test]
+fn document_follows_swaps() {
+    let routes =
+        Swappable::new(Router::new().at("v1").get(m!(ctx => ctx.resp.set_body("v1");)).done());
+    let app = amiya::new()
+        .uses(Router::new().at("openapi.json").is(OpenApi::new("Test", "1.0.0")))
+        .uses(routes.clone());
+    let server = common::serve(app);
+
+    let doc: Value = serde_json::from_str(&server.get("/openapi.json").body).unwrap();
+    assert!(doc["paths"]["/v1"]["get"].is_object());
+    assert!(doc["paths"]["/v2"].is_null());
+
+    routes.swap(Router::new().at("v2").get(m!(ctx => ctx.resp.set_body("v2");)).done());
+    let doc: Value = serde_json::from_str(&server.get("/openapi.json").body).unwrap();
+    assert!(doc["paths"]["/v1"].is_null());
+    assert!(doc["paths"]["/v2"]["get"].is_object());
+}
+
+#[test]
+fn catch_all_param() {
+    let app = amiya::new()
+        .uses(Router::new().at("files/{*path}").get(m!(ctx => ctx.resp.set_body("");)).done());
+    let doc = OpenApi::new("Test", "1.0.0").document(&app.routes());
+
+    let param = &doc["paths"]["/files/{path}"]["get"]["parameters"][0];
+    assert_eq!(param["name"], "path");
+    assert_eq!(param["in"], "path");
+    assert_eq!(param["x-amiya-catch-all"], true);
+}
+
+#[test]
+fn document_of_mounted_app_has_prefix() {
+    #[rustfmt::skip]
+    let api = amiya::new().uses(Router::new()
+        .at("openapi.json").is(OpenApi::new("Test", "1.0.0"))
+        .at("pets").get(m!(ctx => ctx.resp.set_body("[]");)).done()
+    );
+    let app = amiya::new().uses(Router::new().at("api").is(Mount::new(api)));
+    let server = common::serve(app);
+
+    let doc: Value = serde_json::from_str(&server.get("/api/openapi.json").body).unwrap();
+    assert!(doc["paths"]["/api/pets"]["get"].is_object());
+    assert!(doc["paths"]["/pets"].is_null());
+}
+
+#[test]
+fn document_only_for_exact_get() {
+    let app = amiya::new()
+        .uses(Router::new().at("openapi.json").is(OpenApi::new("Test", "1.0.0")))
+        .uses(m!(ctx => ctx.resp.set_body("next");));
+    let server = common::serve(app);
+
+    assert!(server.get("/openapi.json").body.starts_with('{'));
+    assert_eq!(server.get("/openapi.json/more").body, "next");
+    assert_eq!(server.send("POST", "/openapi.json", &[], "{}").body, "next");
+}
@@ -0,0 +1,551 @@
+#![cfg(feature = "built-in-executor")]
+
+mod common;
+
+use amiya::{
+    m,
+    middleware::{DotSegments, GuardRouter, Labeled, MethodRouter, Router, TrailingSlash},
+    StatusCode,
+};
+
+#[test]
+fn catch_all_keeps_encoded_slash() {
+    let router = Router::new()
+        .dot_segments(DotSegments::Reject)
+        .at("files/{*path}")
+        .get(m!(ctx =>
+            let path = ctx.arg("path").unwrap().to_owned();
+            ctx.resp.set_body(path);
+        ))
+        .done();
+    let server = common::serve(amiya::new().uses(router));
+
+    assert_eq!(server.get("/files/a%20b/c.txt").body, "a b/c.txt");
+    assert_eq!(server.get("/files/..%2F..%2Fetc%2Fpasswd").body, "..%2F..%2Fetc%2Fpasswd");
+}
+
+#[test]
+fn matched_route_and_prefix() {
+    #[rustfmt::skip]
+    let router = Router::new()
+        .at("status")
+            .at("{status_code}").get(m!(ctx =>
+                let body = format!("{} {} {}", ctx.matched_route(), ctx.matched_prefix(), ctx.path());
+                ctx.resp.set_body(body);
+            )).done()
+        .done();
+    let app = amiya::new()
+        .uses(m!(ctx => {
+            ctx.next().await?;
+            let route = ctx.matched_route().to_owned();
+            ctx.resp.insert_header("X-Route", route);
+            Ok(())
+        }))
+        .uses(router);
+    let server = common::serve(app);
+
+    let resp = server.get("/status/404");
+    assert_eq!(resp.body, "/status/{status_code} /status/404 ");
+    assert_eq!(resp.header("X-Route"), Some("/status/{status_code}"));
+}
+
+#[test]
+fn url_for_named_route() {
+    #[rustfmt::skip]
+    let router = Router::new()
+        .at("status")
+            .at("{status_code}").name("status").uses(m!(ctx => ctx.resp.set_body("status");)).done()
+        .done()
+        .at("users")
+            .at("{name}").name("user").uses(m!(ctx => ctx.resp.set_body("user");)).done()
+        .done();
+    let app = amiya::new()
+        .uses(m!(ctx =>
+            let body = format!(
+                "{} {} {} {}",
+                ctx.url_for("status", &[("status_code", "404")])?,
+                ctx.url_for("user", &[("name", "a b/c"), ("unused", "x")])?,
+                ctx.url_for("nothing", &[("name", "a")]).unwrap_err().status(),
+                ctx.url_for("user", &[("id", "1")]).unwrap_err().status(),
+            );
+            ctx.resp.set_body(body);
+        ))
+        .uses(router);
+    let server = common::serve(app);
+
+    assert_eq!(server.get("/").body, "/status/404 /users/a%20b%2Fc 500 500");
+}
+
+#[test]
+fn catch_all_arg() {
+    let router = Router::new()
+        .at("files/{*path}")
+        .get(m!(ctx =>
+            let body = format!("{:?} {:?}", ctx.arg("path"), ctx.path());
+            ctx.resp.set_body(body);
+        ))
+        .done();
+    let server = common::serve(amiya::new().uses(router));
+
+    assert_eq!(server.get("/files/a%20b/c.txt").body, "Some(\"a b/c.txt\") \"\"");
+    assert_eq!(server.get("/files/").body, "Some(\"\") \"\"");
+    assert_eq!(server.get("/files").status, 404);
+}
+
+fn status(router: Router<()>) -> Router<()> {
+    router.at("status").get(m!(ctx => ctx.resp.set_body(format!("status {}", ctx.path()));)).done()
+}
+
+#[test]
+fn path_normalization_policies() {
+    #[rustfmt::skip]
+    let router = Router::new()
+        .merge_slashes(true)
+        .trailing_slash(TrailingSlash::Redirect)
+        .at("a")
+            .at("b").get(m!(ctx =>
+                let body = format!("{} {} {}", ctx.matched_prefix(), ctx.path(), ctx.req.url().path());
+                ctx.resp.set_body(body);
+            )).done()
+        .done();
+    let server = common::serve(amiya::new().uses(router));
+
+    // the request is not changed, only the path for routing is normalized
+    assert_eq!(server.get("/a/b").body, "/a/b  /a/b");
+    assert_eq!(server.get("//a///b").body, "/a/b  //a///b");
+    let resp = server.get("/a//b/?x=1");
+    assert_eq!(resp.status, 308);
+    assert_eq!(resp.header("Location"), Some("/a/b?x=1"));
+
+    let server = common::serve(amiya::new().uses(status(Router::new())));
+    assert_eq!(server.get("/status/").status, 404);
+    let router = status(Router::new().trailing_slash(TrailingSlash::Ignore));
+    let server = common::serve(amiya::new().uses(router));
+    assert_eq!(server.get("/status//").body, "status ");
+}
+
+#[test]
+fn trailing_slash_redirect_stays_on_host() {
+    let router = Router::new()
+        .trailing_slash(TrailingSlash::Redirect)
+        .at("evil.com")
+        .get(m!(ctx => ctx.resp.set_body("local");))
+        .done();
+    let server = common::serve(amiya::new().uses(router));
+
+    let resp = server.get("//evil.com/");
+    assert_eq!(resp.status, 308);
+    assert_eq!(resp.header("location"), Some("/evil.com"));
+
+    let resp = server.get("//evil.com//?a=1");
+    assert_eq!(resp.status, 308);
+    assert_eq!(resp.header("location"), Some("/evil.com?a=1"));
+
+    let resp = server.get("///evil.com/");
+    assert_eq!(resp.status, 308);
+    assert_eq!(resp.header("location"), Some("/evil.com"));
+}
+
+#[test]
+fn percent_decoded_match() {
+    #[rustfmt::skip]
+    let router = Router::new()
+        .at("café").get(m!(ctx => ctx.resp.set_body("café");)).done()
+        .at("user/{name}").get(m!(ctx =>
+            let name = ctx.arg("name").unwrap().to_owned();
+            ctx.resp.set_body(name);
+        )).done();
+    let server = common::serve(amiya::new().uses(router));
+
+    assert_eq!(server.get("/caf%C3%A9").body, "café");
+    assert_eq!(server.get("/user/a%20b").body, "a b");
+    assert_eq!(server.get("/user/a%2Fb").body, "a/b");
+    assert_eq!(server.get("/user/a/b").status, 404);
+}
+
+#[test]
+fn case_insensitive_match() {
+    #[rustfmt::skip]
+    let router = Router::new()
+        .case_insensitive(true)
+        .at("Users/{id}.json").get(m!(ctx =>
+            let body = format!("{} {}", ctx.matched_route(), ctx.arg("id").unwrap());
+            ctx.resp.set_body(body);
+        )).done();
+    let server = common::serve(amiya::new().uses(router));
+
+    assert_eq!(server.get("/users/Ab.JSON").body, "/Users/{id}.json Ab");
+    assert_eq!(server.get("/USERS/Ab.json").body, "/Users/{id}.json Ab");
+
+    let router = Router::new().at("Users").get(m!(ctx => ctx.resp.set_body("ok");)).done();
+    let server = common::serve(amiya::new().uses(router));
+    assert_eq!(server.get("/Users").body, "ok");
+    assert_eq!(server.get("/users").status, 404);
+}
+
+#[test]
+fn case_insensitive_covers_former_items() {
+    let router = Router::new()
+        .at("Users")
+        .get(m!(ctx => ctx.resp.set_body(ctx.matched_route().to_owned());))
+        .done()
+        .case_insensitive(true)
+        .at("posts/Latest")
+        .get(m!(ctx => ctx.resp.set_body(ctx.matched_route().to_owned());))
+        .done();
+    let server = common::serve(amiya::new().uses(router));
+
+    assert_eq!(server.get("/USERS").body, "/Users");
+    assert_eq!(server.get("/Posts/latest").body, "/posts/Latest");
+    assert_eq!(server.get("/post/latest").status, 404);
+}
+
+#[test]
+fn list_routes() {
+    #[rustfmt::skip]
+    let router = Router::new()
+        .endpoint().get(m!(ctx => ctx.resp.set_body("index");))
+        .at("status")
+            .get(m!(ctx => ctx.resp.set_body("OK");))
+            .at("{code:uint}").name("status").is(m!(ctx => ctx.resp.set_body("code");))
+        .done()
+        .at("user/{id}").uses(MethodRouter::new()
+            .get(Labeled::new("get user", m!(ctx => ctx.resp.set_body("user");)))
+            .post(m!(ctx => ctx.resp.set_body("update");))
+        ).done()
+        .at("files/{*path}").is(m!(ctx => ctx.resp.set_body("file");))
+        .fallback().uses(m!(ctx => ctx.resp.set_body("fallback");));
+    let app = amiya::new().uses(Router::new().at("api").is(router));
+
+    let routes = app.routes();
+    assert_eq!(routes[3].name.as_deref(), Some("status"));
+    assert_eq!(routes[4].method, Some(amiya::Method::Get));
+    assert_eq!(routes[4].label.as_deref(), Some("get user"));
+    assert_eq!(
+        routes.to_string(),
+        concat!(
+            "GET   /api                     item\n",
+            "*     /api/files/{*path}       catch-all\n",
+            "GET   /api/status              item\n",
+            "*     /api/status/{code:uint}  any-item   @status\n",
+            "GET   /api/user/{id}           any-item   get user\n",
+            "POST  /api/user/{id}           any-item\n",
+            "*     /api                     fallback\n",
+        )
+    );
+}
+
+#[test]
+fn route_conflicts() {
+    #[rustfmt::skip]
+    let result = Router::new()
+        .at("a/b").get(m!(ctx => ctx.resp.set_body("1");)).done()
+        .at("a")
+            .at("b").get(m!(ctx => ctx.resp.set_body("2");)).done()
+        .done()
+        .at("user/{id}").get(m!(ctx => ctx.resp.set_body("id");)).done()
+        .at("user/{name}").post(m!(ctx => ctx.resp.set_body("name");)).done()
+        .at("user/{name}.{ext}").get(m!(ctx => ctx.resp.set_body("ext");)).done()
+        .at("post/{id}").get(m!(ctx => ctx.resp.set_body("id");)).done()
+        .at("post/{slug}").get(m!(ctx => ctx.resp.set_body("slug");)).done()
+        .at("c").get(m!(ctx => ctx.resp.set_body("1");)).done()
+        .at("c").get(m!(ctx => ctx.resp.set_body("2");)).done()
+        .fallback().uses(m!(ctx => ctx.resp.set_body("1");))
+        .fallback().uses(m!(ctx => ctx.resp.set_body("2");))
+        .try_build();
+    let conflict = result.unwrap_err();
+    assert_eq!(
+        conflict.conflicts(),
+        [
+            "`/c` is set more than once",
+            "fallback of `/` is set more than once",
+            "GET `/a/b` is defined more than once",
+            "GET `/post/{id}` and `/post/{slug}` are ambiguous",
+        ]
+    );
+    assert!(conflict.to_string().starts_with("router has 4 route conflict(s):\n  - `/c`"));
+
+    let router = Router::new().at("a").get(m!(ctx => ctx.resp.set_body("a");)).done().build();
+    assert_eq!(router.routes().len(), 1);
+}
+
+#[test]
+fn catch_all_is_not_ambiguous_with_arg() {
+    let result = Router::new()
+        .at("files/{id}")
+        .get(m!(ctx => ctx.resp.set_body("id");))
+        .done()
+        .at("files/{*path}")
+        .get(m!(ctx => ctx.resp.set_body("path");))
+        .done()
+        .try_build();
+    assert!(result.is_ok());
+
+    let result = Router::new()
+        .at("files/{*path}")
+        .get(m!(ctx => ctx.resp.set_body("path");))
+        .done()
+        .at("files/{*rest}")
+        .get(m!(ctx => ctx.resp.set_body("rest");))
+        .done()
+        .try_build();
+    assert!(result.is_err());
+}
+
+#[test]
+fn nested_fallback() {
+    fn router(nested: bool) -> Router<()> {
+        #[rustfmt::skip]
+        let router = Router::new()
+            .nested_fallback(nested)
+            .at("api")
+                .at("v1")
+                    .at("users").get(m!(ctx => ctx.resp.set_body("users");)).done()
+                .done()
+            .done()
+            .at("host")
+                .is(GuardRouter::new().host("example.com", m!(ctx => ctx.resp.set_body("host");)))
+            .fallback()
+                .uses(m!(ctx =>
+                    let body = format!("{} not found", ctx.path());
+                    ctx.resp.set_body(body);
+                ));
+        router
+    }
+
+    let server = common::serve(amiya::new().uses(router(true)));
+    assert_eq!(server.get("/api/v1/users").body, "users");
+    assert_eq!(server.get("/api/v1/posts").body, "/api/v1/posts not found");
+    assert_eq!(server.get("/host").body, "/host not found");
+    assert_eq!(server.request("GET", "/host", &[("Host", "example.com")]).body, "host");
+    assert_eq!(server.get("/posts").body, "/posts not found");
+
+    let server = common::serve(amiya::new().uses(router(false)));
+    assert_eq!(server.get("/api/v1/posts").status, 404);
+    assert_eq!(server.get("/host").status, 404);
+    assert_eq!(server.get("/posts").body, "/posts not found");
+}
+
+#[test]
+fn fallback_bubbling() {
+    #[rustfmt::skip]
+    let router = Router::new()
+        .nested_fallback(true)
+        .at("api")
+            .at("v1")
+                .at("users").get(m!(ctx => ctx.resp.set_body("users");)).done()
+            .done()
+            .fallback()
+                .uses(m!(ctx => ctx.resp.set_body("api fallback");))
+        .done()
+        .at("{page}")
+            .wrap(m!(ctx =>
+                ctx.resp.insert_header("X-Inner", "1");
+                ctx.resp.set_body("inner");
+                ctx.next().await?;
+            ))
+            .at("x").get(m!(ctx => ctx.resp.set_body("x");)).done()
+        .done()
+        .fallback()
+            .uses(m!(ctx =>
+                let page = ctx.arg("page").unwrap_or("none").to_owned();
+                ctx.resp.set_body(format!("top fallback {} {}", ctx.path(), page));
+            ));
+    let server = common::serve(amiya::new().uses(router));
+
+    // `api` has a fallback, so bubbling stops there, but it's not in nested fallback mode
+    assert_eq!(server.get("/api/v1/posts").status, 404);
+    assert_eq!(server.get("/api/posts").body, "api fallback");
+    assert_eq!(server.get("/docs/x").body, "x");
+
+    let resp = server.get("/docs/y");
+    assert_eq!(resp.status, 200);
+    assert_eq!(resp.body, "top fallback /docs/y none");
+    assert_eq!(resp.header("X-Inner"), None);
+}
+
+#[test]
+fn nested_fallback_restores_state() {
+    #[rustfmt::skip]
+    let inner = Router::new()
+        .nested_fallback(true)
+        .at("{page}")
+            .wrap(m!(ctx =>
+                ctx.resp.insert_header("X-Inner", "1");
+                ctx.resp.set_status(StatusCode::Created);
+                ctx.resp.set_body("inner");
+                ctx.next().await?;
+            ))
+            .at("x").get(m!(ctx => ctx.resp.set_body("x");)).done()
+        .done()
+        .fallback()
+            .uses(m!(ctx =>
+                let args = format!("{:?} {:?}", ctx.arg("lang"), ctx.arg("page"));
+                ctx.resp.insert_header("X-Args", args);
+            ));
+    let app = amiya::new().uses(m!(ctx => {
+        ctx.resp.insert_header("X-Outer", "1");
+        ctx.next().await
+    }));
+    let server = common::serve(app.uses(Router::new().at("{lang}").is(inner)));
+
+    assert_eq!(server.get("/en/docs/x").body, "x");
+
+    let resp = server.get("/en/docs/y");
+    assert_eq!(resp.status, 200);
+    assert_eq!(resp.body, "");
+    assert_eq!(resp.header("X-Args"), Some("Some(\"en\") None"));
+    assert_eq!(resp.header("X-Inner"), None);
+    assert_eq!(resp.header("X-Outer"), Some("1"));
+}
+
+fn route() -> amiya::middleware::M<()> {
+    m!(ctx => ctx.resp.set_body(ctx.matched_route().to_owned());)
+}
+
+#[test]
+fn guard_dispatch() {
+    #[rustfmt::skip]
+    let router = Router::new()
+        .at("api")
+            .is(GuardRouter::new()
+                .header("X-Api-Version", "2", m!(ctx => ctx.resp.set_body("api v2");))
+                .content_type("application/json", m!(ctx => ctx.resp.set_body("api v1 json");))
+                .when(|req| req.url().query().is_some(), m!(ctx => ctx.resp.set_body("query");))
+                .fallback(m!(ctx => ctx.resp.set_body("api v1");)));
+    let app = amiya::new().uses(GuardRouter::new().host("example.com", router).fallback(m!(ctx =>
+        ctx.resp.set_status(StatusCode::MisdirectedRequest);
+    )));
+    let server = common::serve(app);
+
+    let host = ("Host", "Example.com:8080");
+    assert_eq!(server.request("GET", "/api", &[host]).body, "api v1");
+    assert_eq!(server.request("GET", "/api?x=1", &[host]).body, "query");
+    let headers = [host, ("X-Api-Version", "2"), ("Content-Type", "application/json")];
+    assert_eq!(server.request("GET", "/api?x=1", &headers).body, "api v2");
+    let headers = [host, ("Content-Type", "application/json; charset=utf-8")];
+    assert_eq!(server.request("GET", "/api?x=1", &headers).body, "api v1 json");
+    assert_eq!(server.request("GET", "/api", &[("Host", "example.org")]).status, 421);
+    assert_eq!(server.get("/api").status, 421);
+
+    let server = common::serve(amiya::new().uses(GuardRouter::new().host("example.com", route())));
+    assert_eq!(server.get("/").status, 404);
+}
+
+#[test]
+fn predicates_are_different_guards() {
+    let result = Router::new()
+        .at("x")
+        .is(GuardRouter::new()
+            .when(|req| req.url().query().is_some(), m!(ctx => ctx.resp.set_body("query");))
+            .when(|req| req.header("X-Debug").is_some(), m!(ctx => ctx.resp.set_body("debug");)))
+        .try_build();
+    assert!(result.is_ok());
+}
+
+#[test]
+fn match_table() {
+    #[rustfmt::skip]
+    let router = Router::new()
+        .at("files/{*path}").get(route()).done()
+        .at("files/readme").get(route()).done()
+        .at("files/{name}.{ext}").get(route()).done()
+        .at("users/{slug}").get(route()).done()
+        .at("users/{id:int}").get(route()).done()
+        .at("users/me").get(route()).done()
+        .at("users/{slug}/posts").get(route()).done()
+        .at("api/{name}/status").get(route()).done()
+        .at("api/v{version:uint}/status").get(route()).done()
+        .at("assets/{name}.js").get(route()).done()
+        .at("assets/{name}.min.js").get(route()).done()
+        .at("colors/{value:hex}").get(route()).done()
+        .at("colors/{name:alpha}").get(route()).done()
+        .at("docs").is(route())
+        .at("docs/api").is(route());
+    let server = common::serve(amiya::new().uses(router));
+
+    let table = [
+        // static, then mixed, then catch-all
+        ("/files/readme", "/files/readme"),
+        ("/files/a.txt", "/files/{name}.{ext}"),
+        ("/files/a", "/files/{*path}"),
+        ("/files/a/b.txt", "/files/{*path}"),
+        // static, then constrained arg, then arg
+        ("/users/me", "/users/me"),
+        ("/users/42", "/users/{id:int}"),
+        ("/users/-7", "/users/{id:int}"),
+        ("/users/4x", "/users/{slug}"),
+        ("/users/bob", "/users/{slug}"),
+        ("/users/bob/posts", "/users/{slug}/posts"),
+        // mixed part with a constraint, then arg
+        ("/api/v2/status", "/api/v{version:uint}/status"),
+        ("/api/vx/status", "/api/{name}/status"),
+        ("/api/v-1/status", "/api/{name}/status"),
+        // mixed parts with more text first
+        ("/assets/app.min.js", "/assets/{name}.min.js"),
+        ("/assets/app.js", "/assets/{name}.js"),
+        // constraints in adding order
+        ("/colors/ff00ff", "/colors/{value:hex}"),
+        ("/colors/red", "/colors/{name:alpha}"),
+        // the longest match wins
+        ("/docs/api/v1", "/docs/api"),
+        ("/docs/guide", "/docs"),
+    ];
+    for (path, expected) in table {
+        let resp = server.get(path);
+        assert_eq!((path, resp.status, resp.body.as_str()), (path, 200, expected));
+    }
+
+    // `/users/me` and `/files/a.txt` are the longest match of the first one, but they have
+    // nothing for the rest
+    let rejected = [
+        "/users/me/posts",
+        "/files/a.txt/b",
+        "/users",
+        "/users/1/2",
+        "/api/v2/other",
+        "/assets/app.css",
+        "/colors/red1",
+    ];
+    for path in rejected {
+        assert_eq!((path, server.get(path).status), (path, 404));
+    }
+}
+
+#[test]
+fn mixed_part_args() {
+    let router = Router::new()
+        .at("files/{name}.{ext}")
+        .get(m!(ctx =>
+            let body = format!("{} {}", ctx.arg("name").unwrap(), ctx.arg("ext").unwrap());
+            ctx.resp.set_body(body);
+        ))
+        .done();
+    let server = common::serve(amiya::new().uses(router));
+
+    // former args take the longest value
+    assert_eq!(server.get("/files/a.tar.gz").body, "a.tar gz");
+}
+
+#[cfg(feature = "regex")]
+#[test]
+fn regex_constraint() {
+    #[rustfmt::skip]
+    let router = Router::new()
+        .at("tags/{tag:[a-z]+-[0-9]+}").get(route()).done()
+        .at("tags/{name}").get(route()).done();
+    let server = common::serve(amiya::new().uses(router));
+
+    assert_eq!(server.get("/tags/rust-2018").body, "/tags/{tag:[a-z]+-[0-9]+}");
+    // the regex must match the whole part
+    assert_eq!(server.get("/tags/rust-2018x").body, "/tags/{name}");
+    assert_eq!(server.get("/tags/Rust-2018").body, "/tags/{name}");
+}
+
+#[test]
+fn invalid_patterns_are_rejected() {
+    for path in ["{id", "id}", "{}", "{a}{b}", "{*rest}/a", "a{*rest}", "{id:nope(}"] {
+        let result = std::panic::catch_unwind(|| Router::<()>::new().at(path).get(route()).done());
+        assert!(result.is_err(), "`{}` should be rejected", path);
+    }
+}
@@ -0,0 +1,98 @@
+#![cfg(feature = "built-in-executor")]
+
+mod common;
+
+use amiya::{
+    m,
+    middleware::{Labeled, MethodRouter, Router, M},
+    routes,
+};
+
+fn body(label: &'static str) -> Labeled<M<()>> {
+    Labeled::new(label, m!(ctx => ctx.resp.set_body(ctx.matched_route().to_owned());))
+}
+
+fn layer() -> M<()> {
+    m!(ctx => {
+        ctx.next().await?;
+        ctx.resp.insert_header("x-layer", "1");
+        Ok(())
+    })
+}
+
+fn by_macro() -> Router<()> {
+    routes! {
+        get => body("index"),
+        head => body("index head"),
+        "api/v1" => wrap(layer()) {
+            "user/{id:uint}" => {
+                get => body("get user"),
+                delete => body("delete user"),
+                r#move => body("move user"),
+            },
+            "files/{*path}" => body("file"),
+            endpoint => body("api"),
+        },
+        "about" => body("about"),
+        fallback => {
+            get => body("not found"),
+        },
+    }
+}
+
+fn by_builder() -> Router<()> {
+    #[rustfmt::skip]
+    let router = Router::new()
+        .endpoint().uses(MethodRouter::new().get(body("index")).head(body("index head")))
+        .at("api/v1").wrap(layer())
+            .at("user/{id:uint}").uses(MethodRouter::new()
+                .get(body("get user"))
+                .delete(body("delete user"))
+                .r#move(body("move user"))
+            ).done()
+            .at("files/{*path}").is(body("file"))
+            .endpoint().uses(body("api"))
+        .done()
+        .at("about").is(body("about"))
+        .fallback().uses(MethodRouter::new().get(body("not found")));
+    router
+}
+
+#[test]
+fn macro_has_same_routes_as_builder() {
+    let by_macro = by_macro();
+    let by_builder = by_builder();
+    assert_eq!(by_macro.routes().len(), 9);
+    assert_eq!(by_macro.routes().to_vec(), by_builder.routes().to_vec());
+    assert!(by_macro.try_build().is_ok());
+}
+
+#[test]
+fn macro_handles_same_requests_as_builder() {
+    let by_macro = common::serve(amiya::new().uses(by_macro()));
+    let by_builder = common::serve(amiya::new().uses(by_builder()));
+
+    let requests = [
+        ("GET", "/"),
+        ("HEAD", "/"),
+        ("POST", "/"),
+        ("GET", "/api/v1"),
+        ("GET", "/api/v1/user/7"),
+        ("DELETE", "/api/v1/user/7"),
+        ("MOVE", "/api/v1/user/7"),
+        ("PUT", "/api/v1/user/7"),
+        ("GET", "/api/v1/user/x"),
+        ("GET", "/api/v1/files/a/b.txt"),
+        ("GET", "/about"),
+        ("GET", "/nothing"),
+        ("POST", "/nothing"),
+    ];
+    for (method, target) in requests {
+        let expected = by_builder.request(method, target, &[]);
+        let got = by_macro.request(method, target, &[]);
+        assert_eq!(got.status, expected.status, "{} {}", method, target);
+        assert_eq!(got.header("allow"), expected.header("allow"), "{} {}", method, target);
+        assert_eq!(got.header("x-layer"), expected.header("x-layer"), "{} {}", method, target);
+        assert_eq!(got.body, expected.body, "{} {}", method, target);
+    }
+}
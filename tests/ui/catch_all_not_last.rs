@@ -0,0 +1,9 @@
+use amiya::get;
+
+#[get("/files/{*path}/raw")]
+async fn get_raw_file(ctx: amiya::Context<'_, ()>, path: String) -> amiya::Result {
+    ctx.resp.set_body(path);
+    Ok(())
+}
+
+fn main() {}
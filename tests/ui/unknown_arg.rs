@@ -0,0 +1,9 @@
+use amiya::get;
+
+#[get("/users/{id}")]
+async fn get_user(ctx: amiya::Context<'_, ()>, name: String) -> amiya::Result {
+    ctx.resp.set_body(name);
+    Ok(())
+}
+
+fn main() {}
@@ -0,0 +1,86 @@
+#![cfg(feature = "built-in-executor")]
+
+mod common;
+
+use amiya::{
+    m,
+    middleware::{Router, VirtualHosts, M},
+    Amiya, BuiltInExecutor,
+};
+
+fn reply(name: &'static str) -> M<()> {
+    m!(ctx =>
+        let body = format!("{} {:?} {:?}", name, ctx.arg("tenant"), ctx.arg("sub"));
+        ctx.resp.set_body(body);
+    )
+}
+
+fn app(fallback: bool) -> Amiya<BuiltInExecutor> {
+    let hosts = VirtualHosts::new()
+        .host("WWW.Example.com.", reply("www"))
+        .host("{tenant}.example.com", reply("tenant"))
+        .host("{*sub}.example.org", reply("sub"));
+    let hosts = if fallback { hosts.fallback(reply("fallback")) } else { hosts };
+    amiya::new().uses(hosts)
+}
+
+fn get(server: &common::Server, host: &str) -> common::Response {
+    server.request("GET", "/", &[("Host", host)])
+}
+
+#[test]
+fn exact_host_ignores_port_case_and_trailing_dot() {
+    let server = common::serve(app(false));
+
+    assert_eq!(get(&server, "www.example.com").body, "www None None");
+    assert_eq!(get(&server, "www.example.com:8080").body, "www None None");
+    assert_eq!(get(&server, "WWW.EXAMPLE.COM").body, "www None None");
+    assert_eq!(get(&server, "www.example.com.").body, "www None None");
+    assert_eq!(get(&server, "Www.Example.Com.:443").body, "www None None");
+}
+
+#[test]
+fn wildcard_labels() {
+    let server = common::serve(app(false));
+
+    assert_eq!(get(&server, "Acme.example.com:8080").body, "tenant Some(\"acme\") None");
+    assert_eq!(get(&server, "a.b.example.org").body, "sub None Some(\"a.b\")");
+    assert_eq!(get(&server, "a.example.org.").body, "sub None Some(\"a\")");
+
+    // a arg matches exactly one label, a catch-all matches at least one
+    assert_eq!(get(&server, "a.b.example.com").status, 421);
+    assert_eq!(get(&server, "example.com").status, 421);
+    assert_eq!(get(&server, "example.org").status, 421);
+}
+
+#[test]
+fn unknown_host_is_421_without_fallback() {
+    let server = common::serve(app(false));
+    assert_eq!(get(&server, "example.net").status, 421);
+
+    let server = common::serve(app(true));
+    let resp = get(&server, "example.net");
+    assert_eq!(resp.status, 200);
+    assert_eq!(resp.body, "fallback None None");
+}
+
+#[test]
+fn several_host_headers_is_400() {
+    let server = common::serve(app(true));
+
+    let resp =
+        server.request("GET", "/", &[("Host", "www.example.com"), ("Host", "a.example.com")]);
+    assert_eq!(resp.status, 400);
+}
+
+#[test]
+fn duplicate_exact_host_is_conflict() {
+    let hosts = VirtualHosts::new()
+        .host("www.example.com", reply("first"))
+        .host("WWW.example.com.", reply("second"))
+        .host("{tenant}.example.com", reply("tenant"));
+    let router = Router::new().at("site").is(hosts);
+
+    let conflicts = router.try_build().unwrap_err().conflicts().to_vec();
+    assert_eq!(conflicts, ["host `www.example.com` of `/site` is set more than once"]);
+}